@@ -15,6 +15,18 @@ import { NestInfo } from "./components/Nest";
 import { cuttingTimeStr } from "./utils";
 import { NestAssign } from "./components/Assign";
 
+type Machine = {
+  name: string;
+  process?: string;
+  maxWidth?: number;
+  maxLength?: number;
+  minThickness?: number;
+  maxThickness?: number;
+  status: "Up" | "Down" | "Maintenance";
+  ncQueue?: string;
+  registered: boolean;
+};
+
 type Program = {
   program: string;
   cuttingTime: number;
@@ -32,7 +44,7 @@ const getPrograms = async (machine: string) => {
   console.log(`Setting machine to ${machine}`);
   localStorage.setItem("machine", machine);
 
-  const response = await fetch(`/api/${machine}`);
+  const response = await fetch(`/api/${machine}?hideUnavailable=true`);
   return response.json();
 };
 
@@ -46,12 +58,12 @@ export const BatchAssign: Component = () => {
   createEffect(() => {
     if (machines()) {
       if (machine() == "" && machines().length > 0) {
-        setMachine(machines()[0]);
+        setMachine(machines()[0].name);
       }
     }
   });
 
-  const [machines, { refetch: fetchMachines }] = createResource<string[], any>(
+  const [machines, { refetch: fetchMachines }] = createResource<Machine[], any>(
     getMachines,
   );
   const [programs, { refetch: fetchPrograms }] = createResource<Program[], any>(
@@ -79,8 +91,8 @@ export const BatchAssign: Component = () => {
       >
         <For each={machines()}>
           {(item) => (
-            <option class="hover:bg-slate-200" value={item}>
-              {item}
+            <option class="hover:bg-slate-200" value={item.name}>
+              {item.name}
             </option>
           )}
         </For>
@@ -3,4 +3,6 @@ export type Batch = {
   mm: string;
   sheetName: string;
   type: string;
+  actualWidth?: number;
+  actualLength?: number;
 };
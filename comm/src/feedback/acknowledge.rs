@@ -0,0 +1,121 @@
+use crate::{
+    db::{SqlConn, Transaction},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+
+/// Feedback SAP has posted and no longer needs
+///
+/// Programs and parts can be acknowledged by `ArchivePacketID`
+/// (all feedback for the nest) or by the `AutoID` of the feedback row.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Acknowledgement {
+    #[serde(default)]
+    pub archive_packet_ids: Vec<i32>,
+    /// `STPrgArc.AutoID`s
+    #[serde(default)]
+    pub program_ids: Vec<i32>,
+    /// `STPIPArc.AutoID`s
+    #[serde(default)]
+    pub part_ids: Vec<i32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcknowledgementResult {
+    /// `STPrgArc.AutoID`s deleted
+    pub deleted_programs: Vec<i32>,
+    /// `STPIPArc.AutoID`s deleted
+    pub deleted_parts: Vec<i32>,
+    /// ids that did not match any feedback
+    pub unknown: Acknowledgement,
+}
+
+impl Acknowledgement {
+    /// delete acknowledged feedback in a single transaction
+    pub async fn delete_feedback(&self, conn: &mut SqlConn<'_>) -> Result<AcknowledgementResult> {
+        let mut tx = Transaction::begin(conn).await?;
+        let result = self.delete_in_transaction(&mut tx).await;
+
+        tx.end(result).await
+    }
+
+    async fn delete_in_transaction(&self, conn: &mut SqlConn<'_>) -> Result<AcknowledgementResult> {
+        let mut result = AcknowledgementResult::default();
+        let mut program_ids = self.program_ids.clone();
+        let mut part_ids = self.part_ids.clone();
+
+        // resolve ArchivePacketIDs to feedback rows
+        for &id in &self.archive_packet_ids {
+            let programs = feedback_ids(
+                conn,
+                "SELECT AutoID FROM dbo.STPrgArc WHERE ArchivePacketID=@P1",
+                id,
+            )
+            .await?;
+            let parts = feedback_ids(
+                conn,
+                "SELECT AutoID FROM dbo.STPIPArc WHERE ArchivePacketID=@P1",
+                id,
+            )
+            .await?;
+
+            if programs.is_empty() && parts.is_empty() {
+                result.unknown.archive_packet_ids.push(id);
+            }
+
+            program_ids.extend(programs);
+            part_ids.extend(parts);
+        }
+        program_ids.sort_unstable();
+        program_ids.dedup();
+        part_ids.sort_unstable();
+        part_ids.dedup();
+
+        for id in program_ids {
+            if feedback_ids(conn, "SELECT AutoID FROM dbo.STPrgArc WHERE AutoID=@P1", id)
+                .await?
+                .is_empty()
+            {
+                result.unknown.program_ids.push(id);
+                continue;
+            }
+
+            conn.execute("EXEC dbo.DeleteProgramFeedback @feedback_id=@P1", &[&id])
+                .await?;
+            result.deleted_programs.push(id);
+        }
+
+        for id in part_ids {
+            if feedback_ids(conn, "SELECT AutoID FROM dbo.STPIPArc WHERE AutoID=@P1", id)
+                .await?
+                .is_empty()
+            {
+                result.unknown.part_ids.push(id);
+                continue;
+            }
+
+            conn.execute(
+                "EXEC dbo.DeletePartInProgressFeedback @feedback_id=@P1",
+                &[&id],
+            )
+            .await?;
+            result.deleted_parts.push(id);
+        }
+
+        Ok(result)
+    }
+}
+
+/// get feedback `AutoID`s for a query with a single id parameter
+async fn feedback_ids(conn: &mut SqlConn<'_>, query: &str, id: i32) -> Result<Vec<i32>> {
+    Ok(conn
+        .query(query, &[&id])
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .filter_map(|row| row.get::<i32, _>("AutoID"))
+        .collect())
+}
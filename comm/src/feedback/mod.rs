@@ -1,11 +1,10 @@
+mod acknowledge;
 mod nest;
 mod part;
 mod program;
-mod remnant;
-mod sheet;
 
-pub use nest::{FeedbackEntry, Nest, TransactionType};
+pub use acknowledge::{Acknowledgement, AcknowledgementResult};
+pub use nest::{get_feedback, get_nest, FeedbackEntry, Nest};
 pub use part::Part;
 pub use program::Program;
-pub use remnant::Remnant;
-pub use sheet::Sheet;
+pub use sndb::model::{Remnant, Sheet};
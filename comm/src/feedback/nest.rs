@@ -1,46 +1,134 @@
 use super::{Part, Program, Remnant, Sheet};
-use crate::Result;
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum TransactionType {
-    Created(Nest),
-    Deleted,
-    Updated,
+use crate::db::{row::SqlRow, DbPool, SqlConn};
+use crate::{Error, Result};
+use sndb::model::{self, FeedbackReport, TransactionType};
+
+/// A posted nest, with its feedback rows and every sheet it is cut from
+pub type Nest = model::Nest<Program, Part, Vec<Sheet>>;
+
+/// A program feedback transaction, with the posted nest for created programs
+pub type FeedbackEntry = model::FeedbackEntry<Nest>;
+
+/// get the posted nest for an ArchivePacketID from feedback
+pub async fn get_nest(db: DbPool, archive_packet_id: i32) -> Result<Nest> {
+    let mut conn = db.get().await?;
+
+    let row = conn
+        .query(
+            r#"
+SELECT
+    AutoID,
+    ArchivePacketID,
+    TransType,
+    ProgramName,
+    RepeatID,
+    MachineName,
+    CuttingTime
+FROM STPrgArc
+WHERE ArchivePacketID=@P1
+AND TransType='SN100'
+    "#,
+            &[&archive_packet_id],
+        )
+        .await?
+        .into_row()
+        .await?;
+
+    let mut nest = match row {
+        Some(row) => read_nest(&row)?,
+        None => {
+            return Err(Error::NotFound(format!(
+                "Nest with ArchivePacketID {} not found in feedback",
+                archive_packet_id
+            )));
+        }
+    };
+    load_feedback(&mut nest, &mut conn).await?;
+
+    Ok(nest)
 }
 
-impl TryFrom<&tiberius::Row> for TransactionType {
-    type Error = crate::Error;
+/// get all program feedback, with the parts, sheets and remnants of posted nests
+///
+/// Nests with rows that cannot be read are skipped so they do not block other feedback.
+pub async fn get_feedback(db: DbPool) -> Result<FeedbackReport<FeedbackEntry>> {
+    let mut conn = db.get().await?;
+
+    let rows = conn
+        .simple_query(
+            r#"
+SELECT
+    AutoID,
+    STPrgArc.ArchivePacketID,
+    TransType,
+    STPrgArc.ProgramName,
+    STPrgArc.RepeatID,
+    MachineName,
+    CuttingTime,
+    ProgramBatch.BatchId
+FROM STPrgArc
+LEFT JOIN ProgramBatch
+    ON ProgramBatch.ArchivePacketID=STPrgArc.ArchivePacketID
+WHERE TransType IN ('SN100', 'SN101', 'SN102')
+    "#,
+        )
+        .await?
+        .into_first_result()
+        .await?;
+    let mut report = FeedbackReport::by_nest(rows.iter().map(|row| {
+        let archive_packet_id = SqlRow::new(row, "STPrgArc").get("ArchivePacketID").ok();
 
-    fn try_from(row: &tiberius::Row) -> Result<TransactionType> {
-        match row.try_get::<&str, _>("TransType")? {
-            Some("SN100") => Ok(Self::Created(todo!("create nest from row"))),
-            Some("SN101") => Ok(Self::Deleted),
-            Some("SN102") => Ok(Self::Updated),
-            _ => unreachable!(),
+        (archive_packet_id, read_entry(row))
+    }))?;
+
+    // nests with parts, sheets or remnants that cannot be read are left out
+    for mut entry in std::mem::take(&mut report.feedback) {
+        if let TransactionType::Created(ref mut nest) = entry.state {
+            if let Err(e) = load_feedback(nest, &mut conn).await {
+                report.skip_nest(entry.archive_packet_id, e)?;
+                continue;
+            }
         }
+
+        report.feedback.push(entry);
     }
+
+    Ok(report)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct FeedbackEntry {
-    pub archive_packet_id: i32,
-    pub state: TransactionType,
+/// load parts, sheets and remnants for the nest
+async fn load_feedback(nest: &mut Nest, conn: &mut SqlConn<'_>) -> Result<()> {
+    nest.parts = Part::get_feedback_by_program(conn, nest.archive_packet_id).await?;
+    nest.sheet = Sheet::get_feedback_by_program(conn, nest.archive_packet_id).await?;
+    nest.remnants = Remnant::get_future_remnants_by_program(
+        conn,
+        nest.program.program.program_name.clone(),
+        nest.program.program.repeat_id,
+    )
+    .await?;
+
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Nest {
-    pub program: Program,
-    pub parts: Vec<Part>,
-    pub sheet: Vec<Sheet>,
-    pub remnants: Vec<Remnant>,
+/// reads a program feedback row, without the parts, sheets and remnants of its nest
+fn read_entry(row: &tiberius::Row) -> Result<FeedbackEntry> {
+    let sql_row = SqlRow::new(row, "STPrgArc");
+
+    Ok(FeedbackEntry {
+        archive_packet_id: sql_row.get("ArchivePacketID")?,
+        batch: sql_row.get_opt_string("BatchId")?,
+        state: TransactionType::from_row_with(&sql_row, |_| read_nest(row))?,
+    })
 }
 
-impl Nest {
-    pub async fn get_nest(db: crate::db::DbPool) -> Result<Self> {
-        todo!()
-    }
+fn read_nest(row: &tiberius::Row) -> Result<Nest> {
+    let program = Program::try_from(row)?;
+
+    Ok(Nest {
+        archive_packet_id: program.archive_packet_id,
+        program,
+        parts: Vec::new(),
+        sheet: Vec::new(),
+        remnants: Vec::new(),
+    })
 }
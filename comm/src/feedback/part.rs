@@ -1,27 +1,31 @@
 use serde::{Deserialize, Serialize};
+use sndb::model;
 
-use super::{Sheet, TransactionType};
-use crate::{db::DbPool, Result};
+use super::Sheet;
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        DbPool, SqlConn,
+    },
+    Result,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Part {
     pub id: i32,
     pub archive_packet_id: i32,
-    pub part_name: String,
-    pub part_qty: i32,
-    pub job: String,
-    pub shipment: i32,
-    pub true_area: f64,
-    pub nested_area: f64,
+    #[serde(flatten)]
+    pub part: model::Part,
     #[serde(flatten)]
     pub sheet: Sheet,
 }
 
 impl Part {
     /// get in process parts from feedback and Part table
-    pub async fn get_feedback(pool: DbPool) -> Result<Vec<Self>> {
-        pool.get()
+    pub async fn get_feedback(pool: DbPool) -> Result<model::FeedbackReport<Self>> {
+        let rows = pool
+            .get()
             .await?
             .simple_query(
                 r#"
@@ -46,10 +50,51 @@ INNER JOIN Stock
             )
             .await?
             .into_first_result()
-            .await?
-            .iter()
-            .map(Self::try_from)
-            .collect()
+            .await?;
+
+        model::FeedbackReport::by_nest(rows.iter().map(|row| {
+            let archive_packet_id = SqlRow::new(row, "STPIPArc").get("ArchivePacketID").ok();
+
+            (archive_packet_id, Self::try_from(row))
+        }))
+    }
+
+    /// get parts posted with a program from feedback
+    pub async fn get_feedback_by_program(
+        conn: &mut SqlConn<'_>,
+        archive_packet_id: i32,
+    ) -> Result<Vec<Self>> {
+        conn.query(
+            r#"
+SELECT
+    AutoID,
+    ArchivePacketID,
+    STPIPArc.PartName,
+    QtyInProcess AS Qty,
+    Data1 AS Job,
+    CAST(Data2 AS INT) AS Shipment,
+    TrueArea,
+    NestedArea,
+    Stock.SheetName,
+    Stock.PrimeCode AS MaterialMaster
+FROM STPIPArc
+INNER JOIN Part
+    ON Part.PartName=STPIPArc.PartName
+    AND Part.WONumber=STPIPArc.WONumber
+INNER JOIN Stock
+    ON STPIPArc.SheetName=Stock.SheetName
+WHERE ArchivePacketID=@P1
+AND TransType='SN100'
+AND QtyInProcess > 0
+        "#,
+            &[&archive_packet_id],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
     }
 }
 
@@ -57,21 +102,12 @@ impl TryFrom<&tiberius::Row> for Part {
     type Error = crate::Error;
 
     fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let sql_row = SqlRow::new(row, "STPIPArc");
+
         Ok(Self {
-            id: row.try_get("AutoID")?.unwrap(),
-            archive_packet_id: row.try_get("ArchivePacketID")?.unwrap(),
-            part_name: row
-                .try_get::<&str, _>("PartName")?
-                .map(Into::into)
-                .unwrap_or_default(),
-            part_qty: row.try_get("Qty")?.unwrap(),
-            job: row
-                .try_get::<&str, _>("Job")?
-                .map(Into::into)
-                .unwrap_or_default(),
-            shipment: row.try_get("Shipment")?.unwrap_or_default(),
-            true_area: row.try_get("TrueArea")?.unwrap(),
-            nested_area: row.try_get("NestedArea")?.unwrap(),
+            id: sql_row.get("AutoID")?,
+            archive_packet_id: sql_row.get("ArchivePacketID")?,
+            part: model::Part::from_row(&sql_row)?,
             sheet: Sheet::try_from(row)?,
         })
     }
@@ -1,21 +1,28 @@
 use serde::{Deserialize, Serialize};
+use sndb::model;
 
-use crate::{db::DbPool, Result};
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        DbPool,
+    },
+    Result,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Program {
     pub id: i32,
     pub archive_packet_id: i32,
-    pub program_name: String,
-    pub machine_name: String,
-    pub cutting_time: f64,
+    #[serde(flatten)]
+    pub program: model::Program,
 }
 
 impl Program {
     /// get in process and updated programs from feedback
-    pub async fn get_feedback(pool: DbPool) -> Result<Vec<Self>> {
-        pool.get()
+    pub async fn get_feedback(pool: DbPool) -> Result<model::FeedbackReport<Self>> {
+        let rows = pool
+            .get()
             .await?
             .simple_query(
                 r#"
@@ -24,6 +31,7 @@ SELECT
     ArchivePacketID,
     TransType,
     ProgramName,
+    RepeatID,
     MachineName,
     CuttingTime
 FROM STPrgArc
@@ -31,10 +39,13 @@ FROM STPrgArc
             )
             .await?
             .into_first_result()
-            .await?
-            .iter()
-            .map(Self::try_from)
-            .collect()
+            .await?;
+
+        model::FeedbackReport::by_nest(rows.iter().map(|row| {
+            let archive_packet_id = SqlRow::new(row, "STPrgArc").get("ArchivePacketID").ok();
+
+            (archive_packet_id, Self::try_from(row))
+        }))
     }
 }
 
@@ -42,18 +53,12 @@ impl TryFrom<&tiberius::Row> for Program {
     type Error = crate::Error;
 
     fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let sql_row = SqlRow::new(row, "STPrgArc");
+
         Ok(Self {
-            id: row.try_get("AutoID")?.unwrap(),
-            archive_packet_id: row.try_get("ArchivePacketID")?.unwrap(),
-            program_name: row
-                .try_get::<&str, _>("ProgramName")?
-                .map(Into::into)
-                .unwrap(),
-            machine_name: row
-                .try_get::<&str, _>("MachineName")?
-                .map(Into::into)
-                .unwrap(),
-            cutting_time: row.try_get("CuttingTime")?.unwrap(),
+            id: sql_row.get("AutoID")?,
+            archive_packet_id: sql_row.get("ArchivePacketID")?,
+            program: model::Program::from_row(&sql_row)?,
         })
     }
 }
@@ -1,17 +1,364 @@
 
-use crate::Result;
-use axum::{http::StatusCode, Json};
+use crate::{db::{row::SqlRow, SqlConn, Transaction}, extract::Json, AppState, Error, Result};
+use axum::{extract::State, http::StatusCode};
 use serde::{Serialize, Deserialize};
+use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::{diff_records, group_by_event, EventStatus, SapSigmanestDiff, Status};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Demand {}
+pub struct Demand {
+	/// SAP event id (numeric 20 positions)
+	pub sap_event_id: Option<String>,
+
+	pub work_order: String,
+	/// Material Master (part name)
+	pub part_name: String,
+	pub qty: i32,
+	/// {spec}-{grade}{test}
+	pub material: String,
+
+	/// State (occurrence)
+	pub state: Option<String>,
+	/// Drawing name
+	pub dwg: Option<String>,
+	/// Autoprocess instruction
+	pub codegen: Option<String>,
+	pub job: Option<String>,
+	pub shipment: Option<String>,
+	/// PART hours order for shipment
+	pub chargeref: Option<String>,
+	/// Secondary operations
+	pub op1: Option<String>,
+	pub op2: Option<String>,
+	pub op3: Option<String>,
+	/// Part name (Material Master with job removed)
+	pub mark: Option<String>,
+	/// Raw material master (from BOM, if exists)
+	pub raw_mm: Option<String>,
+
+	/// Qty completed or in process in Sigmanest, which cannot be removed
+	#[serde(skip)]
+	pub qty_committed: i32,
+}
 
 impl Demand {
-	pub async fn process_sap_events(Json(events): Json<Vec<Self>>) -> Result<StatusCode> {
+	pub async fn process_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<Vec<EventStatus>>)> {
 		log::debug!("{:?}", events);
 
-		// TODO: add failure status reasons
-		Ok(StatusCode::OK)
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+
+		// `dbo.PushSapDemand` does its pre-event processing on the first call
+		// 	for an event id, so all calls for an event need to be together.
+		let mut results = Vec::new();
+		for (event_id, demand) in group_by_event(events, |d| d.sap_event_id.as_deref()) {
+			log::trace!("Pushing {} demand events for SAP event {:?}", demand.len(), event_id);
+
+			for d in demand {
+				let res = d.push(&mut conn, &state.sap_system).await;
+				if let Err(e) = &res {
+					log::error!("Failed to push demand {} ({}): {:?}", d.part_name, d.work_order, e);
+				}
+
+				results.push(EventStatus::new(d.sap_event_id, Some(d.part_name), res));
+			}
+		}
+
+		let status = match results.iter().all(|r| r.success) {
+			true => StatusCode::OK,
+			false => StatusCode::MULTI_STATUS,
+		};
+
+		Ok((status, Json(results)))
+	}
+
+	/// diffs SAP demand with Sigmanest without pushing any transactions
+	pub async fn diff_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<Vec<Status<Self>>>)> {
+		log::debug!("Demand diff requested for {} events", events.len());
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let diff = Self::diff_with_sigmanest(&mut conn, &events).await?;
+
+		Ok((StatusCode::OK, Json(diff)))
+	}
+
+	/// pushes only the transactions needed to bring Sigmanest in line with SAP
+	///
+	/// The sync runs in a single transaction, so a failure leaves Sigmanest as it was.
+	pub async fn sync_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<Vec<Status<Self>>>)> {
+		log::debug!("Demand sync requested for {} events", events.len());
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let mut tx = Transaction::begin(&mut conn).await?;
+		let result = async {
+			let diff = Self::diff_with_sigmanest(&mut tx, &events).await?;
+			for status in diff.iter() {
+				Self::push_status(&mut tx, &state.sap_system, status).await?;
+			}
+
+			Ok(diff)
+		}
+		.await;
+		let diff = tx.end(result).await?;
+
+		Ok((StatusCode::OK, Json(diff)))
+	}
+
+	/// diffs SAP demand with Sigmanest demand of the same part names
+	///
+	/// SAP sends all demand for a part name, so any Sigmanest demand for
+	///     that part name that is not in SAP is to be removed.
+	pub async fn diff_with_sigmanest(conn: &mut SqlConn<'_>, events: &[Self]) -> Result<Vec<Status<Self>>> {
+		let mut part_names: Vec<&str> = events.iter().map(|d| d.part_name.as_str()).collect();
+		part_names.sort_unstable();
+		part_names.dedup();
+
+		let mut results = Vec::new();
+		for part_name in part_names {
+			let sap: Vec<Self> = events
+				.iter()
+				.filter(|d| d.part_name == part_name && d.qty > 0)
+				.cloned()
+				.collect();
+			let sigmanest = Self::get_sigmanest_demand(conn, part_name).await?;
+
+			results.append(&mut diff_records(&sap, &sigmanest));
+		}
+
+		Ok(results)
+	}
+
+	/// get demand in Sigmanest for a part name, from `dbo.SapDemand`
+	pub async fn get_sigmanest_demand(conn: &mut SqlConn<'_>, part_name: &str) -> Result<Vec<Self>> {
+		conn.query("SELECT * FROM dbo.SapDemand WHERE PartName = @P1", &[&part_name])
+			.await?
+			.into_first_result()
+			.await?
+			.iter()
+			.map(Self::try_from)
+			.collect()
+	}
+
+	/// pushes the SimTrans transaction needed for a diff status, if any
+	pub async fn push_status(conn: &mut SqlConn<'_>, sap_system: &str, status: &Status<Self>) -> Result<()> {
+		match status {
+			Status::Same(_) => (),
+			Status::Add(d) | Status::Change(d) => {
+				conn.execute(
+					r#"
+WITH _cfg AS (
+	SELECT TOP 1 SimTransDistrict
+	FROM dbo.SapInterfaceConfig
+	WHERE SapSystem = @P1
+)
+INSERT INTO dbo.TransAct (
+	TransType, District, TransID,
+	OrderNo, ItemName, Qty, Material,
+	Customer, DwgNumber, Remark,
+	ItemData1, ItemData2, ItemData5, ItemData6, ItemData7, ItemData8, ItemData9, ItemData10,
+	ItemData14, ItemData18
+)
+SELECT
+	'SN81', _cfg.SimTransDistrict, RIGHT(@P2, 10),
+	@P3, @P4, @P5 - COALESCE((
+		SELECT SUM(Qty)
+		FROM dbo.SlabPartAllocation
+		WHERE PartName = @P4
+		AND WoNumber = @P3
+	), 0), @P6,
+	@P7, @P8, @P9,
+	@P10, @P11, @P12, @P13, @P14, @P15,
+	COALESCE(@P16, REPLACE(@P4, CONCAT(@P10, '-'), '')), @P17,
+	'HighHeatNum', @P2
+FROM _cfg
+					"#,
+					&[
+						&sap_system,
+						&d.sap_event_id.as_deref(),
+						&d.work_order.as_str(),
+						&d.part_name.as_str(),
+						&d.qty,
+						&d.material.as_str(),
+						&d.state.as_deref(),
+						&d.dwg.as_deref(),
+						&d.codegen.as_deref(),
+						&d.job.as_deref(),
+						&d.shipment.as_deref(),
+						&d.chargeref.as_deref(),
+						&d.op1.as_deref(),
+						&d.op2.as_deref(),
+						&d.op3.as_deref(),
+						&d.mark.as_deref(),
+						&d.raw_mm.as_deref(),
+					],
+				)
+				.await?;
+			}
+			Status::Delete(d) => {
+				// demand with qty completed or in process cannot be deleted,
+				// 	so it is reduced to the qty committed
+				conn.execute(
+					r#"
+WITH _cfg AS (
+	SELECT TOP 1 SimTransDistrict
+	FROM dbo.SapInterfaceConfig
+	WHERE SapSystem = @P1
+)
+INSERT INTO dbo.TransAct (TransType, District, OrderNo, ItemName, Qty)
+SELECT
+	CASE
+		WHEN @P4 = 0
+			THEN 'SN82'
+			ELSE 'SN81'
+	END,
+	_cfg.SimTransDistrict,
+	@P3,
+	@P2,
+	@P4
+FROM _cfg
+					"#,
+					&[&sap_system, &d.part_name.as_str(), &d.work_order.as_str(), &d.qty_committed],
+				)
+				.await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// push demand to Sigmanest through `dbo.PushSapDemand`
+	pub async fn push(&self, conn: &mut SqlConn<'_>, sap_system: &str) -> Result<()> {
+		conn.execute(
+			r#"
+EXEC dbo.PushSapDemand
+	@sap_system=@P1,
+	@sap_event_id=@P2,
+	@work_order=@P3,
+	@part_name=@P4,
+	@qty=@P5,
+	@matl=@P6,
+	@state=@P7,
+	@dwg=@P8,
+	@codegen=@P9,
+	@job=@P10,
+	@shipment=@P11,
+	@chargeref=@P12,
+	@op1=@P13,
+	@op2=@P14,
+	@op3=@P15,
+	@mark=@P16,
+	@raw_mm=@P17
+			"#,
+			&[
+				&sap_system,
+				&self.sap_event_id.as_deref(),
+				&self.work_order.as_str(),
+				&self.part_name.as_str(),
+				&self.qty,
+				&self.material.as_str(),
+				&self.state.as_deref(),
+				&self.dwg.as_deref(),
+				&self.codegen.as_deref(),
+				&self.job.as_deref(),
+				&self.shipment.as_deref(),
+				&self.chargeref.as_deref(),
+				&self.op1.as_deref(),
+				&self.op2.as_deref(),
+				&self.op3.as_deref(),
+				&self.mark.as_deref(),
+				&self.raw_mm.as_deref(),
+			],
+		)
+		.await
+		.map_err(|e| Error::from(e).upstream("dbo.PushSapDemand"))?;
+
+		Ok(())
+	}
+}
+
+impl SapSigmanestDiff for Demand {
+	type Change = Self;
+	type Key = (String, String);
+
+	fn key(&self) -> Self::Key {
+		(self.work_order.clone(), self.part_name.clone())
+	}
+
+	fn added(&self) -> Self::Change {
+		self.clone()
+	}
+
+	fn removed(&self) -> Status<Self::Change> {
+		// demand already reduced to its committed qty is not open demand
+		match self.qty > self.qty_committed {
+			true => Status::Delete(self.clone()),
+			false => Status::Same(self.clone()),
+		}
+	}
+
+	fn diff(&self, other: &Self) -> Status<Self::Change> {
+		// SAP does not send mark if it is derived from the part name
+		let same_mark = self.mark.is_none() || self.mark == other.mark;
+
+		let same = self.qty == other.qty
+			&& self.material == other.material
+			&& self.state == other.state
+			&& self.dwg == other.dwg
+			&& self.codegen == other.codegen
+			&& self.job == other.job
+			&& self.shipment == other.shipment
+			&& self.chargeref == other.chargeref
+			&& self.op1 == other.op1
+			&& self.op2 == other.op2
+			&& self.op3 == other.op3
+			&& same_mark
+			&& self.raw_mm == other.raw_mm;
+
+		match same {
+			true => Status::Same(self.clone()),
+			false => Status::Change(self.clone()),
+		}
+	}
+}
+
+impl TryFrom<&tiberius::Row> for Demand {
+	type Error = crate::Error;
+
+	fn try_from(row: &tiberius::Row) -> Result<Self> {
+		let row = SqlRow::new(row, "SapDemand");
+		let text = |col: &str| row.get_opt_string(col);
+
+		Ok(Self {
+			sap_event_id: text("SapEventId")?,
+			work_order: text("WONumber")?.unwrap_or_default(),
+			part_name: text("PartName")?.unwrap_or_default(),
+			qty: row.get_opt("Qty")?.unwrap_or_default(),
+			material: text("Material")?.unwrap_or_default(),
+			state: text("Customer")?,
+			dwg: text("DwgNumber")?,
+			codegen: text("Remark")?,
+			job: text("Data1")?,
+			shipment: text("Data2")?,
+			chargeref: text("Data5")?,
+			op1: text("Data6")?,
+			op2: text("Data7")?,
+			op3: text("Data8")?,
+			mark: text("Data9")?,
+			raw_mm: text("Data10")?,
+			qty_committed: row.get_opt("QtyCommitted")?.unwrap_or_default(),
+		})
 	}
-}
\ No newline at end of file
+}
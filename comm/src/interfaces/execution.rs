@@ -1,19 +1,79 @@
 
-use crate::Result;
-use axum::{http::StatusCode, Json};
+use crate::{db::{SqlConn, Transaction}, extract::Json, AppState, Error, Result};
+use axum::{extract::State, http::StatusCode};
 use serde::{Serialize, Deserialize};
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Execution {
-	id: u32
+	/// SAP event id (numeric 20 positions)
+	pub sap_event_id: Option<String>,
+	#[serde(alias = "id")]
+	pub archive_packet_id: i32,
 }
 
 impl Execution {
-	pub async fn program_update(Json(exec): Json<Self>) -> Result<StatusCode> {
-		log::debug!("Program update requested with ArchivePackeId: {}", exec.id);
+	pub async fn program_update(
+		State(state): State<Arc<AppState>>,
+		Json(exec): Json<Self>,
+	) -> Result<StatusCode> {
+		log::debug!("Program update requested with ArchivePackeId: {}", exec.archive_packet_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let mut tx = Transaction::begin(&mut conn).await?;
+		let result = async {
+			// `dbo.UpdateProgram` expects the program to exist
+			if !exec.program_exists(&mut tx).await? {
+				return Err(Error::NotFound(format!(
+					"Program with ArchivePacketID {} not found",
+					exec.archive_packet_id
+				)));
+			}
+
+			exec.push(&mut tx, &state.sap_system).await
+		}
+		.await;
+		tx.end(result).await?;
 
-		// TODO: add failure status reasons
 		Ok(StatusCode::OK)
 	}
+
+	/// checks if the program for the ArchivePacketID exists in `dbo.Program`
+	pub async fn program_exists(&self, conn: &mut SqlConn<'_>) -> Result<bool> {
+		let row = conn
+			.query(
+				"SELECT TOP 1 ProgramName FROM dbo.Program WITH (UPDLOCK, HOLDLOCK) WHERE ArchivePacketID=@P1",
+				&[&self.archive_packet_id],
+			)
+			.await?
+			.into_row()
+			.await?;
+
+		Ok(row.is_some())
+	}
+
+	/// update program in Sigmanest through `dbo.UpdateProgram`
+	///
+	/// Completing a slab program also releases the sheets and parts allocated to the slab.
+	pub async fn push(&self, conn: &mut SqlConn<'_>, sap_system: &str) -> Result<()> {
+		conn.execute(
+			r#"
+EXEC dbo.UpdateProgram
+	@sap_system=@P1,
+	@sap_event_id=@P2,
+	@archive_packet_id=@P3
+			"#,
+			&[
+				&sap_system,
+				&self.sap_event_id.as_deref(),
+				&self.archive_packet_id,
+			],
+		)
+		.await
+		.map_err(|e| Error::from(e).upstream("dbo.UpdateProgram"))?;
+
+		Ok(())
+	}
 }
@@ -1,17 +1,586 @@
 
-use crate::Result;
-use axum::{http::StatusCode, Json};
+use crate::{db::{row::SqlRow, SqlConn, Transaction}, extract::{Json, Path}, AppState, Error, Result};
+use axum::{extract::State, http::StatusCode};
 use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+
+use super::{diff_records, group_by_event, same_float, EventStatus, SapSigmanestDiff, Status};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SheetType {
+	/// Full sheet, pushed with `SN91A`
+	#[default]
+	Sheet,
+	/// Remnant, pushed with `SN97` with geometry from the remnant DXF folder
+	Remnant,
+}
+
+impl SheetType {
+	/// value of `@sheet_type` expected by `dbo.PushSapInventory`
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SheetType::Sheet => "Sheet",
+			SheetType::Remnant => "Remnant",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Inventory {
+	/// SAP event id (numeric 20 positions)
+	pub sap_event_id: Option<String>,
+
+	/// Sheet name, or none if SAP has no inventory for the material master
+	pub sheet_name: Option<String>,
+	#[serde(default)]
+	pub sheet_type: SheetType,
+	pub qty: i32,
+	/// {spec}-{grade}{test}
+	pub material: String,
+	pub thickness: f64,
+	/// Remnant geometry comes from its DXF file, so dimensions are optional
+	pub width: Option<f64>,
+	pub length: Option<f64>,
+	pub material_master: String,
+	/// SAP batch number, creates/updates the batch available for assignment
+	#[serde(default)]
+	pub batch: Option<String>,
+
+	/// SAP short text notes
+	pub notes1: Option<String>,
+	pub notes2: Option<String>,
+	pub notes3: Option<String>,
+	pub notes4: Option<String>,
+}
+
+/// Result of pushing SAP inventory events
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryPushResult {
+	pub events: Vec<EventStatus>,
+	/// Sheets held back for slabs, for the material masters in the events
+	pub reservations: Vec<Reservation>,
+}
+
+/// Sheets of a material master allocated to slabs, so Sigmanest stock
+/// is lower than SAP's by `qty`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reservation {
+	pub material_master: String,
+	pub qty: i32,
+	pub slabs: Vec<SlabReservation>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Inventory {}
+pub struct SlabReservation {
+	pub slab_id: i32,
+	pub sheet_name: String,
+	pub qty: i32,
+}
 
 impl Inventory {
-	pub async fn process_sap_events(Json(events): Json<Vec<Self>>) -> Result<StatusCode> {
+	pub async fn process_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<InventoryPushResult>)> {
 		log::debug!("{:?}", events);
 
-		// TODO: add failure status reasons
-		Ok(StatusCode::OK)
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+
+		let material_masters: Vec<String> = material_masters(&events).into_iter().map(String::from).collect();
+
+		// `dbo.PushSapInventory` does its pre-event processing on the first call
+		// 	for an event id, so all calls for an event need to be together.
+		let mut results = Vec::new();
+		for (event_id, inventory) in group_by_event(events, |i| i.sap_event_id.as_deref()) {
+			log::trace!("Pushing {} inventory events for SAP event {:?}", inventory.len(), event_id);
+
+			for i in inventory {
+				let res = i.push(&mut conn, &state.sap_system).await;
+				if let Err(e) = &res {
+					log::error!("Failed to push inventory {:?}: {:?}", i.sheet_name, e);
+				}
+
+				results.push(EventStatus::new(i.sap_event_id, i.sheet_name, res));
+			}
+		}
+
+		let status = match results.iter().all(|r| r.success) {
+			true => StatusCode::OK,
+			false => StatusCode::MULTI_STATUS,
+		};
+
+		let mut reservations = Vec::new();
+		for mm in material_masters {
+			reservations.extend(Self::get_reservations(&mut conn, Some(&mm)).await?);
+		}
+
+		Ok((status, Json(InventoryPushResult { events: results, reservations })))
+	}
+
+	/// sheets held back for slabs, for all material masters
+	pub async fn get_all_reservations(
+		State(state): State<Arc<AppState>>,
+	) -> Result<(StatusCode, Json<Vec<Reservation>>)> {
+		log::debug!("Requested slab reservations");
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let reservations = Self::get_reservations(&mut conn, None).await?;
+
+		Ok((StatusCode::OK, Json(reservations)))
+	}
+
+	/// sheets held back for slabs, for a material master
+	pub async fn get_reservation(
+		State(state): State<Arc<AppState>>,
+		Path(material_master): Path<String>,
+	) -> Result<(StatusCode, Json<Reservation>)> {
+		log::debug!("Requested slab reservations for {}", material_master);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let reservation = Self::get_reservations(&mut conn, Some(&material_master))
+			.await?
+			.pop()
+			.unwrap_or(Reservation {
+				material_master,
+				qty: 0,
+				slabs: Vec::new(),
+			});
+
+		Ok((StatusCode::OK, Json(reservation)))
+	}
+
+	/// get sheets allocated to slabs, grouped by material master
+	///
+	/// These are the sheets `dbo.PushSapInventory` holds back from SAP's qty.
+	pub async fn get_reservations(conn: &mut SqlConn<'_>, material_master: Option<&str>) -> Result<Vec<Reservation>> {
+		let rows = conn
+			.query(
+				r#"
+SELECT
+	MaterialMaster,
+	SlabId,
+	SheetName,
+	COUNT(SheetName) AS Qty
+FROM dbo.SlabSheetAllocation
+WHERE MaterialMaster IS NOT NULL
+AND (@P1 IS NULL OR MaterialMaster = @P1)
+GROUP BY MaterialMaster, SlabId, SheetName
+ORDER BY MaterialMaster, SlabId, SheetName
+				"#,
+				&[&material_master],
+			)
+			.await?
+			.into_first_result()
+			.await?;
+
+		let mut reservations: Vec<Reservation> = Vec::new();
+		for row in rows.iter() {
+			let row = SqlRow::new(row, "SlabSheetAllocation");
+			let mm = row.get_string("MaterialMaster")?;
+			let slab = SlabReservation {
+				slab_id: row.get("SlabId")?,
+				sheet_name: row.get_opt_string("SheetName")?.unwrap_or_default(),
+				qty: row.get("Qty")?,
+			};
+
+			match reservations.last_mut() {
+				Some(reservation) if reservation.material_master == mm => {
+					reservation.qty += slab.qty;
+					reservation.slabs.push(slab);
+				}
+				_ => reservations.push(Reservation {
+					material_master: mm,
+					qty: slab.qty,
+					slabs: vec![slab],
+				}),
+			}
+		}
+
+		Ok(reservations)
+	}
+
+	/// diffs SAP inventory with Sigmanest without pushing any transactions
+	pub async fn diff_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<Vec<Status<Self>>>)> {
+		log::debug!("Inventory diff requested for {} events", events.len());
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let diff = Self::diff_with_sigmanest(&mut conn, &events).await?;
+
+		Ok((StatusCode::OK, Json(diff)))
+	}
+
+	/// pushes only the transactions needed to bring Sigmanest in line with SAP
+	///
+	/// Stock and batches are synced in a single transaction, so a failure
+	///     leaves Sigmanest and the batch registry as they were.
+	pub async fn sync_sap_events(
+		State(state): State<Arc<AppState>>,
+		Json(events): Json<Vec<Self>>,
+	) -> Result<(StatusCode, Json<Vec<Status<Self>>>)> {
+		log::debug!("Inventory sync requested for {} events", events.len());
+
+		for event in events.iter() {
+			event.validate()?;
+		}
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let mut tx = Transaction::begin(&mut conn).await?;
+		let result = async {
+			let diff = Self::diff_with_sigmanest(&mut tx, &events).await?;
+			for status in diff.iter() {
+				Self::push_status(&mut tx, &state.sap_system, status).await?;
+			}
+			Self::sync_batches(&mut tx, &events).await?;
+
+			Ok(diff)
+		}
+		.await;
+		let diff = tx.end(result).await?;
+
+		Ok((StatusCode::OK, Json(diff)))
+	}
+
+	/// brings the batch registry in line with SAP inventory
+	///
+	/// Like stock, SAP sends all batches for a material master, so batches of
+	///     the material master that SAP did not send (or sent with qty 0) are retired.
+	/// Run it in a transaction, so batches are not left retired if a push fails.
+	pub async fn sync_batches(conn: &mut SqlConn<'_>, events: &[Self]) -> Result<()> {
+		let material_masters = material_masters(events);
+
+		for mm in material_masters {
+			conn.execute(
+				"UPDATE dbo.Batch SET Retired = 1, Updated = SYSDATETIME() WHERE MaterialMaster = @P1 AND Retired = 0",
+				&[&mm],
+			)
+			.await?;
+		}
+
+		for event in events {
+			event.push_batch(conn).await?;
+		}
+
+		Ok(())
+	}
+
+	/// add, update or retire the event's batch through `dbo.PushSapBatch`
+	pub async fn push_batch(&self, conn: &mut SqlConn<'_>) -> Result<()> {
+		conn.execute(
+			r#"
+EXEC dbo.PushSapBatch
+	@batch=@P1,
+	@sheet_name=@P2,
+	@sheet_type=@P3,
+	@qty=@P4,
+	@mm=@P5
+			"#,
+			&[
+				&self.batch.as_deref(),
+				&self.sheet_name.as_deref(),
+				&self.sheet_type.as_str(),
+				&self.qty,
+				&self.material_master.as_str(),
+			],
+		)
+		.await
+		.map_err(|e| Error::from(e).upstream("dbo.PushSapBatch"))?;
+
+		Ok(())
+	}
+
+	/// diffs SAP inventory with Sigmanest stock of the same material masters
+	///
+	/// SAP sends all inventory for a material master, so any Sigmanest stock
+	///     for that material master that is not in SAP is to be removed.
+	pub async fn diff_with_sigmanest(conn: &mut SqlConn<'_>, events: &[Self]) -> Result<Vec<Status<Self>>> {
+		let material_masters = material_masters(events);
+
+		let mut results = Vec::new();
+		for mm in material_masters {
+			let sap: Vec<Self> = events
+				.iter()
+				.filter(|i| i.material_master == mm && i.sheet_name.is_some() && i.qty > 0)
+				.cloned()
+				.collect();
+			let sigmanest = Self::get_sigmanest_stock(conn, mm).await?;
+
+			results.append(&mut diff_records(&sap, &sigmanest));
+		}
+
+		Ok(results)
+	}
+
+	/// get stock in Sigmanest for a material master
+	///
+	/// Qty includes any sheets allocated to slabs, so it is comparable to SAP's qty.
+	pub async fn get_sigmanest_stock(conn: &mut SqlConn<'_>, material_master: &str) -> Result<Vec<Self>> {
+		conn.query(
+			r#"
+SELECT
+	BinNumber AS SapEventId,
+	SheetName,
+	Qty + (
+		SELECT COUNT(SheetName)
+		FROM dbo.SlabSheetAllocation AS _slab
+		WHERE _slab.SheetName = _stock.SheetName
+	) AS Qty,
+	Material,
+	Thickness,
+	Width,
+	Length,
+	PrimeCode AS MaterialMaster
+FROM dbo.Stock AS _stock
+WHERE PrimeCode = @P1
+			"#,
+			&[&material_master],
+		)
+		.await?
+		.into_first_result()
+		.await?
+		.iter()
+		.map(Self::try_from)
+		.collect()
+	}
+
+	/// pushes the SimTrans transaction needed for a diff status, if any
+	pub async fn push_status(conn: &mut SqlConn<'_>, sap_system: &str, status: &Status<Self>) -> Result<()> {
+		match status {
+			Status::Same(_) => (),
+			Status::Add(i) | Status::Change(i) => {
+				conn.execute(
+					r#"
+WITH _cfg AS (
+	SELECT TOP 1
+		RemnantDxfTemplate,
+		SimTransDistrict
+	FROM dbo.SapInterfaceConfig
+	WHERE SapSystem = @P1
+)
+INSERT INTO dbo.TransAct (
+	TransType, District, TransID,
+	ItemName, Qty, Material, Thickness, Width, Length, PrimeCode, BinNumber,
+	ItemData1, ItemData2, ItemData3, ItemData4,
+	FileName
+)
+SELECT
+	CASE @P4
+		WHEN 'Remnant' THEN 'SN97'
+		ELSE 'SN91A'
+	END,
+	_cfg.SimTransDistrict,
+	RIGHT(@P2, 10),
+	@P3,
+	@P5 - (
+		SELECT COUNT(SheetName)
+		FROM dbo.SlabSheetAllocation
+		WHERE SheetName = @P3
+	),
+	@P6, @P7, @P8, @P9, @P10, @P2,
+	@P11, @P12, @P13, @P14,
+	CASE @P4
+		WHEN 'Remnant'
+			THEN REPLACE(_cfg.RemnantDxfTemplate, '<sheet_name>', @P3)
+		ELSE NULL
+	END
+FROM _cfg
+					"#,
+					&[
+						&sap_system,
+						&i.sap_event_id.as_deref(),
+						&i.sheet_name.as_deref(),
+						&i.sheet_type.as_str(),
+						&i.qty,
+						&i.material.as_str(),
+						&i.thickness,
+						&i.width,
+						&i.length,
+						&i.material_master.as_str(),
+						&i.notes1.as_deref(),
+						&i.notes2.as_deref(),
+						&i.notes3.as_deref(),
+						&i.notes4.as_deref(),
+					],
+				)
+				.await?;
+			}
+			Status::Delete(i) => {
+				// SimTrans requires material and dimensions for SN91A
+				conn.execute(
+					r#"
+WITH _cfg AS (
+	SELECT TOP 1 SimTransDistrict
+	FROM dbo.SapInterfaceConfig
+	WHERE SapSystem = @P1
+)
+INSERT INTO dbo.TransAct (TransType, District, ItemName, Qty, Material, Thickness, Length, Width)
+SELECT 'SN91A', _cfg.SimTransDistrict, @P2, 0, @P3, @P4, @P5, @P6
+FROM _cfg
+					"#,
+					&[
+						&sap_system,
+						&i.sheet_name.as_deref(),
+						&i.material.as_str(),
+						&i.thickness,
+						&i.length,
+						&i.width,
+					],
+				)
+				.await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// checks the event has the data `dbo.PushSapInventory` needs for its sheet type
+	pub fn validate(&self) -> Result<()> {
+		if self.qty > 0 && self.sheet_name.is_none() {
+			return Err(Error::Validation(String::from("sheet name required when qty > 0")));
+		}
+
+		// remnants get their geometry from the DXF template
+		if self.sheet_type == SheetType::Sheet
+			&& self.qty > 0
+			&& (self.width.is_none() || self.length.is_none())
+		{
+			return Err(Error::Validation(format!(
+				"sheet {:?} requires width and length",
+				self.sheet_name
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// push inventory to Sigmanest through `dbo.PushSapInventory`
+	pub async fn push(&self, conn: &mut SqlConn<'_>, sap_system: &str) -> Result<()> {
+		self.validate()?;
+
+		conn.execute(
+			r#"
+EXEC dbo.PushSapInventory
+	@sap_system=@P1,
+	@sap_event_id=@P2,
+	@sheet_name=@P3,
+	@sheet_type=@P4,
+	@qty=@P5,
+	@matl=@P6,
+	@thk=@P7,
+	@wid=@P8,
+	@len=@P9,
+	@mm=@P10,
+	@notes1=@P11,
+	@notes2=@P12,
+	@notes3=@P13,
+	@notes4=@P14,
+	@batch=@P15
+			"#,
+			&[
+				&sap_system,
+				&self.sap_event_id.as_deref(),
+				&self.sheet_name.as_deref(),
+				&self.sheet_type.as_str(),
+				&self.qty,
+				&self.material.as_str(),
+				&self.thickness,
+				&self.width,
+				&self.length,
+				&self.material_master.as_str(),
+				&self.notes1.as_deref(),
+				&self.notes2.as_deref(),
+				&self.notes3.as_deref(),
+				&self.notes4.as_deref(),
+				&self.batch.as_deref(),
+			],
+		)
+		.await
+		.map_err(|e| Error::from(e).upstream("dbo.PushSapInventory"))?;
+
+		Ok(())
+	}
+}
+
+/// material masters of the events, without duplicates
+fn material_masters(events: &[Inventory]) -> Vec<&str> {
+	let mut material_masters: Vec<&str> = events.iter().map(|i| i.material_master.as_str()).collect();
+	material_masters.sort_unstable();
+	material_masters.dedup();
+
+	material_masters
+}
+
+impl SapSigmanestDiff for Inventory {
+	type Change = Self;
+	type Key = Option<String>;
+
+	fn key(&self) -> Self::Key {
+		self.sheet_name.clone()
+	}
+
+	fn added(&self) -> Self::Change {
+		self.clone()
+	}
+
+	fn removed(&self) -> Status<Self::Change> {
+		Status::Delete(self.clone())
+	}
+
+	fn diff(&self, other: &Self) -> Status<Self::Change> {
+		// remnant dimensions come from geometry, so SAP may not send them
+		let same_dim = |sap: Option<f64>, sn: Option<f64>| match (sap, sn) {
+			(None, _) => true,
+			(Some(sap), Some(sn)) => same_float(sap, sn),
+			(Some(_), None) => false,
+		};
+
+		let same = self.qty == other.qty
+			&& self.material == other.material
+			&& same_float(self.thickness, other.thickness)
+			&& same_dim(self.width, other.width)
+			&& same_dim(self.length, other.length)
+			&& self.material_master == other.material_master;
+
+		match same {
+			true => Status::Same(self.clone()),
+			false => Status::Change(self.clone()),
+		}
+	}
+}
+
+impl TryFrom<&tiberius::Row> for Inventory {
+	type Error = crate::Error;
+
+	fn try_from(row: &tiberius::Row) -> Result<Self> {
+		let row = SqlRow::new(row, "Stock");
+		let text = |col: &str| row.get_opt_string(col);
+
+		Ok(Self {
+			sap_event_id: text("SapEventId")?,
+			sheet_name: text("SheetName")?,
+			sheet_type: SheetType::default(),
+			qty: row.get_opt("Qty")?.unwrap_or_default(),
+			material: text("Material")?.unwrap_or_default(),
+			thickness: row.get_opt("Thickness")?.unwrap_or_default(),
+			width: row.get_opt("Width")?,
+			length: row.get_opt("Length")?,
+			material_master: text("MaterialMaster")?.unwrap_or_default(),
+			batch: None,
+			notes1: None,
+			notes2: None,
+			notes3: None,
+			notes4: None,
+		})
 	}
 }
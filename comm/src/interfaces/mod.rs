@@ -1,24 +1,234 @@
 
+use serde::Serialize;
+
 mod demand;
 mod execution;
 mod inventory;
 mod nest;
+mod slab;
 
 pub use demand::Demand;
 pub use execution::Execution;
-pub use inventory::Inventory;
+pub use inventory::{Inventory, InventoryPushResult, Reservation, SheetType, SlabReservation};
 pub use nest::Nest;
+pub use slab::{Slab, SlabFeedback, SlabPart, SlabPartFeedback, SlabSheet, SlabSheetFeedback};
 
-#[derive(Debug)]
+/// Difference between SAP and Sigmanest for a record
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", content = "item", rename_all = "camelCase")]
 pub enum Status<T> {
-	Same,
+	/// No transaction needed
+	Same(T),
+	/// Only in SAP
 	Add(T),
-	Delete,
+	/// Only in Sigmanest
+	Delete(T),
+	/// In both, but SAP differs
 	Change(T),
 }
 
+impl<T> Status<T> {
+	pub fn is_same(&self) -> bool {
+		matches!(self, Status::Same(_))
+	}
+}
+
+/// Result of pushing a single SAP event to Sigmanest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStatus {
+	pub sap_event_id: Option<String>,
+	/// Item the event is for (sheet name, part name, etc.)
+	pub item: Option<String>,
+	pub success: bool,
+	/// Failure reason, if the event was not pushed
+	pub reason: Option<String>,
+}
+
+impl EventStatus {
+	pub fn new(sap_event_id: Option<String>, item: Option<String>, result: crate::Result<()>) -> Self {
+		let reason = match result {
+			Ok(()) => None,
+			Err(e) => Some(e.to_string()),
+		};
+
+		Self {
+			sap_event_id,
+			item,
+			success: reason.is_none(),
+			reason,
+		}
+	}
+}
+
+/// Groups events by SAP event id, keeping the order each event id was first seen
+pub(crate) fn group_by_event<T, F>(events: Vec<T>, event_id: F) -> Vec<(Option<String>, Vec<T>)>
+where
+	F: Fn(&T) -> Option<&str>,
+{
+	let mut groups: Vec<(Option<String>, Vec<T>)> = Vec::new();
+	for event in events {
+		let id = event_id(&event).map(String::from);
+		match groups.iter_mut().find(|(key, _)| *key == id) {
+			Some((_, group)) => group.push(event),
+			None => groups.push((id, vec![event])),
+		}
+	}
+
+	groups
+}
+
+/// Largest difference between SAP and Sigmanest values (i.e. dimensions)
+///     that are considered the same, since they are stored as floats
+const FLOAT_TOLERANCE: f64 = 1e-4;
+
+pub(crate) fn same_float(sap: f64, sigmanest: f64) -> bool {
+	(sap - sigmanest).abs() < FLOAT_TOLERANCE
+}
+
 pub trait SapSigmanestDiff {
 	type Change;
+	type Key: PartialEq;
+
+	/// identifies the same record in SAP and Sigmanest
+	fn key(&self) -> Self::Key;
+
+	/// change for a record that is only in SAP
+	fn added(&self) -> Self::Change;
+
+	/// status of a record that is only in Sigmanest
+	fn removed(&self) -> Status<Self::Change>;
+
+	/// compares a SAP record (`self`) to its Sigmanest record (`other`)
+	fn diff(&self, other: &Self) -> Status<Self::Change>;
+}
+
+/// Diffs SAP records against the Sigmanest records for the same scope
+///     (i.e. material master) so that only needed transactions are issued.
+pub fn diff_records<T: SapSigmanestDiff>(sap: &[T], sigmanest: &[T]) -> Vec<Status<T::Change>> {
+	let mut results: Vec<Status<T::Change>> = sap
+		.iter()
+		.map(|s| match sigmanest.iter().find(|sn| sn.key() == s.key()) {
+			Some(sn) => s.diff(sn),
+			None => Status::Add(s.added()),
+		})
+		.collect();
+
+	results.extend(
+		sigmanest
+			.iter()
+			.filter(|sn| !sap.iter().any(|s| s.key() == sn.key()))
+			.map(|sn| sn.removed()),
+	);
+
+	results
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, Clone, PartialEq)]
+	struct Record {
+		key: &'static str,
+		value: f64,
+	}
+
+	impl SapSigmanestDiff for Record {
+		type Change = Self;
+		type Key = &'static str;
+
+		fn key(&self) -> Self::Key {
+			self.key
+		}
+
+		fn added(&self) -> Self::Change {
+			self.clone()
+		}
+
+		fn removed(&self) -> Status<Self::Change> {
+			Status::Delete(self.clone())
+		}
+
+		fn diff(&self, other: &Self) -> Status<Self::Change> {
+			match same_float(self.value, other.value) {
+				true => Status::Same(self.clone()),
+				false => Status::Change(self.clone()),
+			}
+		}
+	}
+
+	fn record(key: &'static str, value: f64) -> Record {
+		Record { key, value }
+	}
+
+	fn statuses(results: &[Status<Record>]) -> Vec<(&'static str, &'static str)> {
+		results
+			.iter()
+			.map(|status| match status {
+				Status::Same(r) => ("same", r.key),
+				Status::Add(r) => ("add", r.key),
+				Status::Delete(r) => ("delete", r.key),
+				Status::Change(r) => ("change", r.key),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn diff_records_statuses() {
+		let sap = [record("a", 1.0), record("b", 2.0), record("c", 3.0)];
+		let sigmanest = [record("d", 4.0), record("b", 2.5), record("a", 1.0)];
+
+		assert_eq!(
+			statuses(&diff_records(&sap, &sigmanest)),
+			[("same", "a"), ("change", "b"), ("add", "c"), ("delete", "d")]
+		);
+	}
+
+	#[test]
+	fn diff_records_empty() {
+		assert!(diff_records::<Record>(&[], &[]).is_empty());
+		assert_eq!(statuses(&diff_records(&[record("a", 1.0)], &[])), [("add", "a")]);
+		assert_eq!(statuses(&diff_records(&[], &[record("a", 1.0)])), [("delete", "a")]);
+	}
+
+	#[test]
+	fn diff_records_float_tolerance() {
+		// 0.1 + 0.2 is stored as 0.30000000000000004
+		let sap = [record("a", 0.3), record("b", 0.75)];
+		let sigmanest = [record("a", 0.1 + 0.2), record("b", 0.751)];
+
+		assert_eq!(statuses(&diff_records(&sap, &sigmanest)), [("same", "a"), ("change", "b")]);
+	}
+
+	#[test]
+	fn committed_demand_is_not_deleted() {
+		let demand = |qty, qty_committed| Demand {
+			sap_event_id: None,
+			work_order: String::from("1200055A-1"),
+			part_name: String::from("1200055A-X1"),
+			qty,
+			material: String::from("50W"),
+			state: None,
+			dwg: None,
+			codegen: None,
+			job: None,
+			shipment: None,
+			chargeref: None,
+			op1: None,
+			op2: None,
+			op3: None,
+			mark: None,
+			raw_mm: None,
+			qty_committed,
+		};
+
+		// open qty is removed, down to the qty committed
+		let diff = diff_records(&[], &[demand(4, 1)]);
+		assert!(matches!(diff[..], [Status::Delete(ref d)] if d.qty_committed == 1));
 
-    fn diff(&self, other: &Self) -> Status<Self::Change>;
+		// all qty is in process or completed
+		let diff = diff_records(&[], &[demand(4, 4)]);
+		assert!(matches!(diff[..], [Status::Same(_)]));
+	}
 }
@@ -1,22 +1,62 @@
 
-use crate::Result;
-use axum::{http::StatusCode, Json};
+use crate::feedback::{self, Acknowledgement, AcknowledgementResult, FeedbackEntry, Part, Program};
+use crate::{extract::{Json, Path}, AppState, Result};
+use axum::{extract::State, http::StatusCode};
 use serde::{Serialize, Deserialize};
+use sndb::model::FeedbackReport;
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Nest {}
 
 impl Nest {
-	pub async fn get_feedback() -> Result<(StatusCode, Json<Vec<Self>>)> {
+	pub async fn get_feedback(
+		State(state): State<Arc<AppState>>,
+	) -> Result<(StatusCode, Json<FeedbackReport<FeedbackEntry>>)> {
 		log::debug!("Requested feedback");
 
-		// TODO: add failure status reasons
-		Ok((StatusCode::OK, Json(vec![])))
+		let state = Arc::clone(&state);
+		let feedback = feedback::get_feedback(state.db.clone()).await?;
+
+		Ok((StatusCode::OK, Json(feedback)))
 	}
-	async fn get_program_feedback(
+
+	pub async fn get_nest(
 		State(state): State<Arc<AppState>>,
-	) -> Result<(StatusCode, Json<Vec<Program>>)> {
+		Path(archive_packet_id): Path<i32>,
+	) -> Result<(StatusCode, Json<feedback::Nest>)> {
+		log::debug!("Requested nest feedback for ArchivePacketID {}", archive_packet_id);
+
+		let state = Arc::clone(&state);
+		let nest = feedback::get_nest(state.db.clone(), archive_packet_id).await?;
+
+		Ok((StatusCode::OK, Json(nest)))
+	}
+
+	/// delete feedback that SAP has posted
+	pub async fn acknowledge_feedback(
+		State(state): State<Arc<AppState>>,
+		Json(ack): Json<Acknowledgement>,
+	) -> Result<(StatusCode, Json<AcknowledgementResult>)> {
+		log::debug!("Feedback acknowledged: {:?}", ack);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let result = ack.delete_feedback(&mut conn).await?;
+
+		log::info!(
+			"Deleted {} program and {} part feedback rows",
+			result.deleted_programs.len(),
+			result.deleted_parts.len()
+		);
+
+		Ok((StatusCode::OK, Json(result)))
+	}
+
+	pub async fn get_program_feedback(
+		State(state): State<Arc<AppState>>,
+	) -> Result<(StatusCode, Json<FeedbackReport<Program>>)> {
 		log::debug!("Requested programs feedback");
 
 		let state = Arc::clone(&state);
@@ -25,14 +65,14 @@ impl Nest {
 		Ok((StatusCode::OK, Json(feedback)))
 	}
 
-	async fn get_part_feedback(
+	pub async fn get_part_feedback(
 		State(state): State<Arc<AppState>>,
-	) -> Result<(StatusCode, Json<Vec<Part>>)> {
+	) -> Result<(StatusCode, Json<FeedbackReport<Part>>)> {
 		log::debug!("Requested parts feedback");
 
 		let state = Arc::clone(&state);
 		let feedback = Part::get_feedback(state.db.clone()).await?;
 
 		Ok((StatusCode::OK, Json(feedback)))
+	}
 }
-}
\ No newline at end of file
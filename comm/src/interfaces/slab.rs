@@ -0,0 +1,784 @@
+use crate::{db::{row::SqlRow, SqlConn, Transaction}, extract::{Json, Path}, AppState, Error, Result};
+use axum::{extract::State, http::StatusCode};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+
+/// Tolerance for parts placed on the edge of a sheet
+const POSITION_TOLERANCE: f64 = 1e-6;
+
+/// A slab is nested in Sigmanest as a single sheet, made up of physical sheets
+/// placed on it.
+///
+/// Allocated sheets and parts are held back from SAP inventory and demand
+/// until the slab program is completed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Slab {
+	pub slab_id: i32,
+	/// Sigmanest sheet the slab is nested on
+	pub sheet_name: Option<String>,
+	#[serde(default)]
+	pub sheets: Vec<SlabSheet>,
+	#[serde(default)]
+	pub parts: Vec<SlabPart>,
+}
+
+/// A physical sheet placed on a slab
+///
+/// Position is the sheet's origin corner on the slab, with length along the
+/// X axis and width along the Y axis before rotation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlabSheet {
+	/// order the sheet was allocated in, set when the sheet is allocated
+	#[serde(default)]
+	pub sheet_index: i32,
+	pub sheet_name: String,
+	/// material master of the sheet, from Sigmanest stock when it was allocated
+	#[serde(default, skip_deserializing)]
+	pub material_master: Option<String>,
+	pub x_position: f64,
+	pub y_position: f64,
+	/// degrees, counterclockwise
+	#[serde(default)]
+	pub rotation: f64,
+	pub width: f64,
+	pub length: f64,
+}
+
+/// A part placed on a slab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlabPart {
+	pub part_name: String,
+	pub wo_number: String,
+	pub qty: i32,
+	pub x_position: f64,
+	pub y_position: f64,
+	/// degrees, counterclockwise
+	#[serde(default)]
+	pub rotation: f64,
+}
+
+/// Slab feedback, with posted parts split by the physical sheet they are cut from
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlabFeedback {
+	pub slab_id: i32,
+	/// posted program nested on the slab, if there is one in feedback
+	pub archive_packet_id: Option<i32>,
+	pub sheets: Vec<SlabSheetFeedback>,
+	/// posted parts that are not on any of the slab's sheets
+	pub unplaced: Vec<SlabPartFeedback>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlabSheetFeedback {
+	#[serde(flatten)]
+	pub sheet: SlabSheet,
+	pub parts: Vec<SlabPartFeedback>,
+}
+
+/// A part posted in the slab's program (`STPIPArc`)
+///
+/// Posted parts have no position, so they are placed on sheets by the slab's
+/// part allocations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlabPartFeedback {
+	pub part_name: String,
+	pub wo_number: String,
+	pub qty: i32,
+}
+
+impl SlabSheet {
+	/// checks if a point on the slab is on this sheet
+	pub fn contains(&self, x: f64, y: f64) -> bool {
+		// rotate the point into the sheet's coordinates
+		let (sin, cos) = (-self.rotation.to_radians()).sin_cos();
+		let (dx, dy) = (x - self.x_position, y - self.y_position);
+		let along_length = dx * cos - dy * sin;
+		let along_width = dx * sin + dy * cos;
+
+		let within = |value: f64, max: f64| (-POSITION_TOLERANCE..=max + POSITION_TOLERANCE).contains(&value);
+		within(along_length, self.length) && within(along_width, self.width)
+	}
+
+	fn validate(&self) -> Result<()> {
+		let dimensions = [self.width, self.length];
+		if dimensions.iter().any(|d| !d.is_finite() || *d <= 0.0) {
+			return Err(Error::Validation(format!(
+				"sheet {} must have a positive width and length",
+				self.sheet_name
+			)));
+		}
+
+		let position = [self.x_position, self.y_position, self.rotation];
+		if position.iter().any(|p| !p.is_finite()) {
+			return Err(Error::Validation(format!("sheet {} has an invalid position", self.sheet_name)));
+		}
+
+		Ok(())
+	}
+}
+
+impl SlabPart {
+	fn validate(&self) -> Result<()> {
+		if self.qty <= 0 {
+			return Err(Error::Validation(format!("part {} qty must be positive", self.part_name)));
+		}
+
+		let position = [self.x_position, self.y_position, self.rotation];
+		if position.iter().any(|p| !p.is_finite()) {
+			return Err(Error::Validation(format!("part {} has an invalid position", self.part_name)));
+		}
+
+		Ok(())
+	}
+}
+
+impl Slab {
+	pub async fn create_slab(
+		State(state): State<Arc<AppState>>,
+		Json(slab): Json<Self>,
+	) -> Result<(StatusCode, Json<Self>)> {
+		log::info!("Creating slab {}", slab.slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		slab.create(&mut conn).await?;
+		let slab = Self::get(&mut conn, slab.slab_id).await?;
+
+		Ok((StatusCode::CREATED, Json(slab)))
+	}
+
+	pub async fn get_slab(
+		State(state): State<Arc<AppState>>,
+		Path(slab_id): Path<i32>,
+	) -> Result<(StatusCode, Json<Self>)> {
+		log::debug!("Requested slab {}", slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let slab = Self::get(&mut conn, slab_id).await?;
+
+		Ok((StatusCode::OK, Json(slab)))
+	}
+
+	pub async fn allocate_sheets(
+		State(state): State<Arc<AppState>>,
+		Path(slab_id): Path<i32>,
+		Json(sheets): Json<Vec<SlabSheet>>,
+	) -> Result<(StatusCode, Json<Self>)> {
+		log::info!("Allocating {} sheets to slab {}", sheets.len(), slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		Self::get(&mut conn, slab_id).await?;
+		let mut tx = Transaction::begin(&mut conn).await?;
+		let result = Self::push_sheets(&mut tx, slab_id, &sheets).await;
+		tx.end(result).await?;
+		let slab = Self::get(&mut conn, slab_id).await?;
+
+		Ok((StatusCode::OK, Json(slab)))
+	}
+
+	pub async fn allocate_parts(
+		State(state): State<Arc<AppState>>,
+		Path(slab_id): Path<i32>,
+		Json(parts): Json<Vec<SlabPart>>,
+	) -> Result<(StatusCode, Json<Self>)> {
+		log::info!("Allocating {} parts to slab {}", parts.len(), slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		Self::get(&mut conn, slab_id).await?;
+		let mut tx = Transaction::begin(&mut conn).await?;
+		let result = Self::push_parts(&mut tx, slab_id, &parts).await;
+		tx.end(result).await?;
+		let slab = Self::get(&mut conn, slab_id).await?;
+
+		Ok((StatusCode::OK, Json(slab)))
+	}
+
+	/// releases all sheets and parts allocated to a slab
+	pub async fn release_slab(
+		State(state): State<Arc<AppState>>,
+		Path(slab_id): Path<i32>,
+	) -> Result<StatusCode> {
+		log::info!("Releasing allocations of slab {}", slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		Self::get(&mut conn, slab_id).await?;
+		Self::release(&mut conn, slab_id).await?;
+
+		Ok(StatusCode::NO_CONTENT)
+	}
+
+	/// get slab feedback, with posted parts split by physical sheet
+	pub async fn get_feedback(
+		State(state): State<Arc<AppState>>,
+		Path(slab_id): Path<i32>,
+	) -> Result<(StatusCode, Json<SlabFeedback>)> {
+		log::debug!("Requested feedback for slab {}", slab_id);
+
+		let state = Arc::clone(&state);
+		let mut conn = state.db.get().await?;
+		let slab = Self::get(&mut conn, slab_id).await?;
+		let archive_packet_id = Self::get_posted_program(&mut conn, slab_id).await?;
+		let posted = match archive_packet_id {
+			Some(id) => Self::get_posted_parts(&mut conn, id).await?,
+			None => Vec::new(),
+		};
+
+		let mut feedback = slab.split_parts(&posted);
+		feedback.archive_packet_id = archive_packet_id;
+
+		Ok((StatusCode::OK, Json(feedback)))
+	}
+
+	/// get a slab with its allocated sheets and parts
+	pub async fn get(conn: &mut SqlConn<'_>, slab_id: i32) -> Result<Self> {
+		let row = conn
+			.query("SELECT SlabId, SheetName FROM dbo.Slab WHERE SlabId=@P1", &[&slab_id])
+			.await?
+			.into_row()
+			.await?
+			.ok_or_else(|| Error::NotFound(format!("Slab {} not found", slab_id)))?;
+		let row = SqlRow::new(&row, "Slab");
+		let mut slab = Self {
+			slab_id: row.get("SlabId")?,
+			sheet_name: row.get_opt_string("SheetName")?,
+			sheets: Vec::new(),
+			parts: Vec::new(),
+		};
+
+		slab.sheets = conn
+			.query(
+				r#"
+SELECT
+	SheetIndex,
+	SheetName,
+	MaterialMaster,
+	XPosition,
+	YPosition,
+	Rotation,
+	Width,
+	Length
+FROM dbo.SlabSheetAllocation
+WHERE SlabId = @P1
+ORDER BY SheetIndex
+				"#,
+				&[&slab_id],
+			)
+			.await?
+			.into_first_result()
+			.await?
+			.iter()
+			.map(SlabSheet::try_from)
+			.collect::<Result<_>>()?;
+
+		slab.parts = conn
+			.query(
+				r#"
+SELECT
+	PartName,
+	WoNumber,
+	Qty,
+	XPosition,
+	YPosition,
+	Rotation
+FROM dbo.SlabPartAllocation
+WHERE SlabId = @P1
+ORDER BY Id
+				"#,
+				&[&slab_id],
+			)
+			.await?
+			.into_first_result()
+			.await?
+			.iter()
+			.map(SlabPart::try_from)
+			.collect::<Result<_>>()?;
+
+		Ok(slab)
+	}
+
+	/// adds a slab with its sheet and part allocations in a single transaction
+	pub async fn create(&self, conn: &mut SqlConn<'_>) -> Result<()> {
+		let mut tx = Transaction::begin(conn).await?;
+		let result = self.create_in_transaction(&mut tx).await;
+		tx.end(result).await
+	}
+
+	async fn create_in_transaction(&self, conn: &mut SqlConn<'_>) -> Result<()> {
+		conn.execute(
+			"INSERT INTO dbo.Slab(SlabId, SheetName) VALUES (@P1, @P2)",
+			&[&self.slab_id, &self.sheet_name.as_deref()],
+		)
+		.await
+		.map_err(|e| Error::from(e).on_duplicate(|| format!("Slab {} already exists", self.slab_id)))?;
+
+		Self::push_sheets(conn, self.slab_id, &self.sheets).await?;
+		Self::push_parts(conn, self.slab_id, &self.parts).await
+	}
+
+	/// allocates physical sheets to a slab, after any sheets already on it
+	///
+	/// A sheet can be allocated to a slab only once, and to no more slabs than its qty in stock.
+	async fn push_sheets(conn: &mut SqlConn<'_>, slab_id: i32, sheets: &[SlabSheet]) -> Result<()> {
+		for sheet in sheets {
+			sheet.validate()?;
+
+			let stock = conn
+				.query(
+					r#"
+SELECT
+	Qty,
+	(
+		SELECT COUNT(SheetName)
+		FROM dbo.SlabSheetAllocation WITH (UPDLOCK, HOLDLOCK)
+		WHERE SheetName = @P1
+	) AS Allocated,
+	(
+		SELECT COUNT(SheetName)
+		FROM dbo.SlabSheetAllocation WITH (UPDLOCK, HOLDLOCK)
+		WHERE SheetName = @P1 AND SlabId = @P2
+	) AS OnSlab
+FROM dbo.Stock
+WHERE SheetName = @P1
+					"#,
+					&[&sheet.sheet_name.as_str(), &slab_id],
+				)
+				.await?
+				.into_row()
+				.await?;
+			let Some(stock) = stock else {
+				return Err(Error::Validation(format!("Sheet {} is not in stock", sheet.sheet_name)));
+			};
+
+			let stock = SqlRow::new(&stock, "Stock");
+			let qty: i32 = stock.get_opt("Qty")?.unwrap_or_default();
+			let allocated: i32 = stock.get("Allocated")?;
+			if stock.get::<i32>("OnSlab")? > 0 {
+				return Err(Error::Conflict(format!(
+					"Sheet {} is already allocated to slab {}",
+					sheet.sheet_name, slab_id
+				)));
+			}
+			if allocated >= qty {
+				return Err(Error::Conflict(format!(
+					"Sheet {} has no stock left to allocate ({} in stock, {} allocated to slabs)",
+					sheet.sheet_name, qty, allocated
+				)));
+			}
+
+			conn.execute(
+				r#"
+INSERT INTO dbo.SlabSheetAllocation (
+	SlabId, SheetIndex, SheetName, MaterialMaster,
+	XPosition, YPosition, Rotation, Width, Length
+)
+SELECT
+	@P1,
+	COALESCE(MAX(SheetIndex) + 1, 0),
+	@P2,
+	(SELECT TOP 1 PrimeCode FROM dbo.Stock WHERE SheetName = @P2),
+	@P3, @P4, @P5, @P6, @P7
+FROM dbo.SlabSheetAllocation
+WHERE SlabId = @P1
+				"#,
+				&[
+					&slab_id,
+					&sheet.sheet_name.as_str(),
+					&sheet.x_position,
+					&sheet.y_position,
+					&sheet.rotation,
+					&sheet.width,
+					&sheet.length,
+				],
+			)
+			.await
+			.map_err(|e| {
+				Error::from(e).on_duplicate(|| {
+					format!("Sheet {} is already allocated to slab {}", sheet.sheet_name, slab_id)
+				})
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// allocates work order parts to a slab
+	async fn push_parts(conn: &mut SqlConn<'_>, slab_id: i32, parts: &[SlabPart]) -> Result<()> {
+		for part in parts {
+			part.validate()?;
+
+			let on_order = conn
+				.query(
+					"SELECT 1 FROM dbo.Part WHERE PartName=@P1 AND WONumber=@P2",
+					&[&part.part_name.as_str(), &part.wo_number.as_str()],
+				)
+				.await?
+				.into_row()
+				.await?;
+			if on_order.is_none() {
+				return Err(Error::Validation(format!(
+					"Part {} is not on work order {}",
+					part.part_name, part.wo_number
+				)));
+			}
+
+			conn.execute(
+				r#"
+IF NOT EXISTS (SELECT 1 FROM dbo.SlabParts WHERE SlabId=@P1 AND PartName=@P2)
+	INSERT INTO dbo.SlabParts(SlabId, PartName, Qty) VALUES (@P1, @P2, 0);
+
+UPDATE dbo.SlabParts
+SET Qty = Qty + @P4
+WHERE SlabId = @P1 AND PartName = @P2;
+
+INSERT INTO dbo.SlabPartAllocation (
+	SlabId, SlabPartId, PartName, WoNumber, Qty,
+	XPosition, YPosition, Rotation
+)
+SELECT @P1, Id, @P2, @P3, @P4, @P5, @P6, @P7
+FROM dbo.SlabParts
+WHERE SlabId = @P1 AND PartName = @P2
+				"#,
+				&[
+					&slab_id,
+					&part.part_name.as_str(),
+					&part.wo_number.as_str(),
+					&part.qty,
+					&part.x_position,
+					&part.y_position,
+					&part.rotation,
+				],
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+
+	/// releases all sheets and parts allocated to a slab
+	///
+	/// `dbo.UpdateProgram` does the same when a slab program is completed.
+	pub async fn release(conn: &mut SqlConn<'_>, slab_id: i32) -> Result<()> {
+		let mut tx = Transaction::begin(conn).await?;
+		let result = async {
+			tx.execute(
+				r#"
+DELETE FROM dbo.SlabPartAllocation WHERE SlabId = @P1;
+DELETE FROM dbo.SlabParts WHERE SlabId = @P1;
+DELETE FROM dbo.SlabSheetAllocation WHERE SlabId = @P1;
+				"#,
+				&[&slab_id],
+			)
+			.await?;
+
+			Ok(())
+		}
+		.await;
+		tx.end(result).await
+	}
+
+	/// get the ArchivePacketID of the posted program nested on a slab
+	async fn get_posted_program(conn: &mut SqlConn<'_>, slab_id: i32) -> Result<Option<i32>> {
+		let row = conn
+			.query(
+				r#"
+SELECT TOP 1 STPrgArc.ArchivePacketID
+FROM dbo.STPrgArc
+INNER JOIN dbo.SIP
+	ON  STPrgArc.ProgramName = SIP.ProgramName
+	AND STPrgArc.RepeatID = SIP.RepeatID
+INNER JOIN dbo.Slab
+	ON Slab.SheetName = SIP.SheetName
+WHERE Slab.SlabId = @P1
+AND STPrgArc.TransType = 'SN100'
+ORDER BY STPrgArc.AutoID DESC
+				"#,
+				&[&slab_id],
+			)
+			.await?
+			.into_row()
+			.await?;
+
+		match row {
+			Some(row) => Ok(Some(SqlRow::new(&row, "STPrgArc").get("ArchivePacketID")?)),
+			None => Ok(None),
+		}
+	}
+
+	/// get the parts posted in a program
+	async fn get_posted_parts(conn: &mut SqlConn<'_>, archive_packet_id: i32) -> Result<Vec<SlabPartFeedback>> {
+		conn
+			.query(
+				r#"
+SELECT
+	PartName,
+	WONumber,
+	QtyInProcess
+FROM dbo.STPIPArc
+WHERE ArchivePacketID = @P1
+AND TransType = 'SN100'
+AND QtyInProcess > 0
+ORDER BY AutoID
+				"#,
+				&[&archive_packet_id],
+			)
+			.await?
+			.into_first_result()
+			.await?
+			.iter()
+			.map(SlabPartFeedback::try_from)
+			.collect()
+	}
+
+	/// splits posted parts by the physical sheet they are cut from
+	///
+	/// Each posted part takes up the slab's allocations of the same part and
+	/// work order in the order they were allocated, and goes to the sheet each
+	/// allocation is placed on. Allocations on overlapping sheets go to the
+	/// first sheet allocated. Posted qty that is not allocated, or allocated
+	/// off every sheet, is unplaced.
+	pub fn split_parts(&self, posted: &[SlabPartFeedback]) -> SlabFeedback {
+		let mut sheets: Vec<SlabSheetFeedback> = self
+			.sheets
+			.iter()
+			.map(|sheet| SlabSheetFeedback {
+				sheet: sheet.clone(),
+				parts: Vec::new(),
+			})
+			.collect();
+		let mut unplaced = Vec::new();
+		let mut allocated: Vec<i32> = self.parts.iter().map(|part| part.qty).collect();
+
+		for part in posted {
+			let mut remaining = part.qty;
+
+			for (allocation, available) in self.parts.iter().zip(allocated.iter_mut()) {
+				if remaining == 0 {
+					break;
+				}
+				if allocation.part_name != part.part_name || allocation.wo_number != part.wo_number {
+					continue;
+				}
+
+				let qty = remaining.min(*available);
+				if qty == 0 {
+					continue;
+				}
+				*available -= qty;
+				remaining -= qty;
+
+				let placed = sheets
+					.iter_mut()
+					.find(|s| s.sheet.contains(allocation.x_position, allocation.y_position))
+					.map(|s| &mut s.parts);
+				add_part(placed.unwrap_or(&mut unplaced), part, qty);
+			}
+
+			if remaining > 0 {
+				add_part(&mut unplaced, part, remaining);
+			}
+		}
+
+		SlabFeedback {
+			slab_id: self.slab_id,
+			archive_packet_id: None,
+			sheets,
+			unplaced,
+		}
+	}
+}
+
+/// adds qty of a posted part to a list, merging it with the same part
+fn add_part(parts: &mut Vec<SlabPartFeedback>, part: &SlabPartFeedback, qty: i32) {
+	match parts
+		.iter_mut()
+		.find(|p| p.part_name == part.part_name && p.wo_number == part.wo_number)
+	{
+		Some(existing) => existing.qty += qty,
+		None => parts.push(SlabPartFeedback { qty, ..part.clone() }),
+	}
+}
+
+impl TryFrom<&tiberius::Row> for SlabSheet {
+	type Error = crate::Error;
+
+	fn try_from(row: &tiberius::Row) -> Result<Self> {
+		let row = SqlRow::new(row, "SlabSheetAllocation");
+
+		Ok(Self {
+			sheet_index: row.get("SheetIndex")?,
+			sheet_name: row.get_opt_string("SheetName")?.unwrap_or_default(),
+			material_master: row.get_opt_string("MaterialMaster")?,
+			x_position: row.get_opt("XPosition")?.unwrap_or_default(),
+			y_position: row.get_opt("YPosition")?.unwrap_or_default(),
+			rotation: row.get_opt("Rotation")?.unwrap_or_default(),
+			width: row.get_opt("Width")?.unwrap_or_default(),
+			length: row.get_opt("Length")?.unwrap_or_default(),
+		})
+	}
+}
+
+impl TryFrom<&tiberius::Row> for SlabPart {
+	type Error = crate::Error;
+
+	fn try_from(row: &tiberius::Row) -> Result<Self> {
+		let row = SqlRow::new(row, "SlabPartAllocation");
+
+		Ok(Self {
+			part_name: row.get_opt_string("PartName")?.unwrap_or_default(),
+			wo_number: row.get_opt_string("WoNumber")?.unwrap_or_default(),
+			qty: row.get_opt("Qty")?.unwrap_or_default(),
+			x_position: row.get_opt("XPosition")?.unwrap_or_default(),
+			y_position: row.get_opt("YPosition")?.unwrap_or_default(),
+			rotation: row.get_opt("Rotation")?.unwrap_or_default(),
+		})
+	}
+}
+
+impl TryFrom<&tiberius::Row> for SlabPartFeedback {
+	type Error = crate::Error;
+
+	fn try_from(row: &tiberius::Row) -> Result<Self> {
+		let row = SqlRow::new(row, "STPIPArc");
+
+		Ok(Self {
+			part_name: row.get_opt_string("PartName")?.unwrap_or_default(),
+			wo_number: row.get_opt_string("WONumber")?.unwrap_or_default(),
+			qty: row.get_opt("QtyInProcess")?.unwrap_or_default(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sheet(sheet_name: &str, x_position: f64, y_position: f64, rotation: f64) -> SlabSheet {
+		SlabSheet {
+			sheet_index: 0,
+			sheet_name: sheet_name.into(),
+			material_master: None,
+			x_position,
+			y_position,
+			rotation,
+			width: 10.0,
+			length: 20.0,
+		}
+	}
+
+	fn allocation(part_name: &str, qty: i32, x_position: f64, y_position: f64) -> SlabPart {
+		SlabPart {
+			part_name: part_name.into(),
+			wo_number: "WO1".into(),
+			qty,
+			x_position,
+			y_position,
+			rotation: 0.0,
+		}
+	}
+
+	fn posted(part_name: &str, qty: i32) -> SlabPartFeedback {
+		SlabPartFeedback {
+			part_name: part_name.into(),
+			wo_number: "WO1".into(),
+			qty,
+		}
+	}
+
+	fn slab(sheets: Vec<SlabSheet>, parts: Vec<SlabPart>) -> Slab {
+		Slab {
+			slab_id: 1,
+			sheet_name: Some("SLAB1".into()),
+			sheets,
+			parts,
+		}
+	}
+
+	#[test]
+	fn contains() {
+		let sheet = sheet("S1", 5.0, 5.0, 0.0);
+
+		assert!(sheet.contains(15.0, 10.0));
+		assert!(!sheet.contains(4.0, 10.0));
+		assert!(!sheet.contains(15.0, 16.0));
+	}
+
+	#[test]
+	fn contains_edge() {
+		let sheet = sheet("S1", 0.0, 0.0, 0.0);
+
+		assert!(sheet.contains(0.0, 0.0));
+		assert!(sheet.contains(20.0, 10.0));
+		assert!(sheet.contains(20.0 + POSITION_TOLERANCE / 2.0, 5.0));
+		assert!(!sheet.contains(20.0 + POSITION_TOLERANCE * 2.0, 5.0));
+		assert!(!sheet.contains(-POSITION_TOLERANCE * 2.0, 5.0));
+	}
+
+	#[test]
+	fn contains_rotated() {
+		// length runs up the Y axis and width back along -X
+		let sheet = sheet("S1", 100.0, 0.0, 90.0);
+
+		assert!(sheet.contains(95.0, 15.0));
+		assert!(sheet.contains(90.0, 20.0));
+		assert!(!sheet.contains(105.0, 15.0));
+		assert!(!sheet.contains(115.0, 5.0));
+	}
+
+	#[test]
+	fn split_parts() {
+		let slab = slab(
+			vec![sheet("S1", 0.0, 0.0, 0.0), sheet("S2", 20.0, 0.0, 0.0)],
+			vec![allocation("P1", 2, 5.0, 5.0), allocation("P2", 1, 25.0, 5.0), allocation("P1", 1, 30.0, 5.0)],
+		);
+		let feedback = slab.split_parts(&[posted("P1", 3), posted("P2", 1)]);
+
+		assert_eq!(feedback.sheets[0].parts, vec![posted("P1", 2)]);
+		assert_eq!(feedback.sheets[1].parts, vec![posted("P1", 1), posted("P2", 1)]);
+		assert!(feedback.unplaced.is_empty());
+	}
+
+	#[test]
+	fn split_parts_overlapping() {
+		// S2 overlaps S1, so parts on both go to S1
+		let slab = slab(
+			vec![sheet("S1", 0.0, 0.0, 0.0), sheet("S2", 10.0, 0.0, 0.0)],
+			vec![allocation("P1", 1, 15.0, 5.0), allocation("P2", 1, 25.0, 5.0)],
+		);
+		let feedback = slab.split_parts(&[posted("P1", 1), posted("P2", 1)]);
+
+		assert_eq!(feedback.sheets[0].parts, vec![posted("P1", 1)]);
+		assert_eq!(feedback.sheets[1].parts, vec![posted("P2", 1)]);
+	}
+
+	#[test]
+	fn split_parts_unplaced() {
+		let slab = slab(
+			vec![sheet("S1", 0.0, 0.0, 0.0)],
+			vec![allocation("P1", 1, 5.0, 5.0), allocation("P2", 1, 50.0, 50.0)],
+		);
+		// P1 is posted more than allocated, P2 is off the sheet and P3 is not allocated
+		let feedback = slab.split_parts(&[posted("P1", 2), posted("P2", 1), posted("P3", 1)]);
+
+		assert_eq!(feedback.sheets[0].parts, vec![posted("P1", 1)]);
+		assert_eq!(feedback.unplaced, vec![posted("P1", 1), posted("P2", 1), posted("P3", 1)]);
+	}
+
+	#[test]
+	fn split_parts_not_posted() {
+		let slab = slab(vec![sheet("S1", 0.0, 0.0, 0.0)], vec![allocation("P1", 1, 5.0, 5.0)]);
+		let feedback = slab.split_parts(&[]);
+
+		assert!(feedback.sheets[0].parts.is_empty());
+		assert!(feedback.unplaced.is_empty());
+	}
+}
@@ -2,39 +2,35 @@ pub mod db;
 pub mod feedback;
 pub mod interfaces;
 
-pub mod error {
-    use axum::{
-        http::StatusCode,
-        response::{IntoResponse, Response},
-    };
+pub use sndb::{config, error, extract, Error, Result};
 
-    // Error handling: see
-    //  https://docs.rs/axum/latest/axum/error_handling/index.html
-    //  https://github.com/tokio-rs/axum/blob/main/examples/anyhow-error-response/src/main.rs
-    #[derive(Debug, thiserror::Error)]
-    pub enum Error {
-        #[error("Database error: see server logs.")]
-        SqlError(#[from] tiberius::error::Error),
-        #[error("Database pool error: see server logs.")]
-        SqlPoolError,
-        #[error("Requested resource not found")]
-        NotFound(String),
-    }
+/// Shared state for the interface routes
+#[derive(Debug)]
+pub struct AppState {
+    pub db: db::DbPool,
+    pub db_health: std::sync::Arc<db::DbHealth>,
 
-    // Tell axum how to convert `AppError` into a response.
-    impl IntoResponse for Error {
-        fn into_response(self) -> Response {
-            (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
-        }
-    }
+    /// Name of SAP system (PRD, QAS, etc.) this service pushes data for.
+    /// Matches a `SapSystem` row in `dbo.SapInterfaceConfig`.
+    pub sap_system: String,
+}
 
-    impl<T: std::fmt::Debug> From<bb8::RunError<T>> for Error {
-        fn from(value: bb8::RunError<T>) -> Self {
-            log::error!("Casting bb8 error to app error: {:#?}", value);
-            Self::SqlPoolError
-        }
+impl AppState {
+    pub fn new() -> Result<Self> {
+        let config = config::DbConfig::load()?;
+        let sap_system = std::env::var("SAP_SYSTEM")
+            .ok()
+            .or_else(|| config.profile.clone())
+            .unwrap_or_else(|| String::from("QAS"));
+
+        let db = db::build_db_pool(&config)?;
+        let db_health = std::sync::Arc::new(db::DbHealth::default());
+        db_health.monitor(db.clone());
+
+        Ok(Self {
+            db,
+            db_health,
+            sap_system,
+        })
     }
 }
-
-pub use error::Error;
-pub type Result<T> = std::result::Result<T, Error>;
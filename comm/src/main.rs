@@ -1,28 +1,14 @@
 use axum::extract::State;
 use axum::http::StatusCode;
+use axum::middleware;
 use axum::routing::{get, post};
-use axum::{Json, Router};
+use axum::Router;
 
-use comm::db;
-use comm::feedback::{Part, Program};
-use comm::Result;
 use comm::interfaces;
+use comm::AppState;
 
 use std::sync::Arc;
 
-#[derive(Debug)]
-pub struct AppState {
-    pub db: db::DbPool,
-}
-
-impl AppState {
-    pub async fn new() -> Self {
-        Self {
-            db: db::build_db_pool().await,
-        }
-    }
-}
-
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     fern::Dispatch::new()
@@ -54,13 +40,45 @@ async fn main() -> std::io::Result<()> {
         .apply()
         .expect("failed to init logging");
 
-    let state = Arc::new(AppState::new().await);
+    let state = match AppState::new() {
+        Ok(state) => Arc::new(state),
+        Err(e) => {
+            log::error!("failed to start server: {}", e);
+            return Err(std::io::Error::other(e.to_string()));
+        }
+    };
     let app = Router::new()
         .route("/", get(|| async { "root request not implemented yet" }))
+        .route("/health", get(get_health))
         .route("/demand", post(interfaces::Demand::process_sap_events))
+        .route("/demand/diff", post(interfaces::Demand::diff_sap_events))
+        .route("/demand/sync", post(interfaces::Demand::sync_sap_events))
         .route("/execution", post(interfaces::Execution::program_update))
         .route("/inventory", post(interfaces::Inventory::process_sap_events))
+        .route("/inventory/diff", post(interfaces::Inventory::diff_sap_events))
+        .route("/inventory/sync", post(interfaces::Inventory::sync_sap_events))
+        .route(
+            "/inventory/reservations",
+            get(interfaces::Inventory::get_all_reservations),
+        )
+        .route(
+            "/inventory/reservations/:mm",
+            get(interfaces::Inventory::get_reservation),
+        )
         .route("/feedback", get(interfaces::Nest::get_feedback))
+        .route("/feedback/ack", post(interfaces::Nest::acknowledge_feedback))
+        .route("/feedback/programs", get(interfaces::Nest::get_program_feedback))
+        .route("/feedback/parts", get(interfaces::Nest::get_part_feedback))
+        .route("/feedback/:id", get(interfaces::Nest::get_nest))
+        .route("/feedback/slab/:id", get(interfaces::Slab::get_feedback))
+        .route("/slab", post(interfaces::Slab::create_slab))
+        .route(
+            "/slab/:id",
+            get(interfaces::Slab::get_slab).delete(interfaces::Slab::release_slab),
+        )
+        .route("/slab/:id/sheets", post(interfaces::Slab::allocate_sheets))
+        .route("/slab/:id/parts", post(interfaces::Slab::allocate_parts))
+        .layer(middleware::from_fn(comm::error::with_correlation_id))
         .with_state(state);
 
     // run our app with hyper, listening globally on port 3000
@@ -68,3 +86,9 @@ async fn main() -> std::io::Result<()> {
     axum::serve(listener, app).await
 }
 
+async fn get_health(State(state): State<Arc<AppState>>) -> comm::Result<StatusCode> {
+    match state.db_health.is_available() {
+        true => Ok(StatusCode::OK),
+        false => Err(comm::Error::SqlPoolError),
+    }
+}
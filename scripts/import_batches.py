@@ -0,0 +1,40 @@
+
+# One-time import of the batches exported to `server/batches.csv` into dbo.Batch
+
+from os import getenv, path
+from lib.db import SndbConnection
+
+import csv
+
+db_kwargs = dict(
+    server="HIISQLSERV6",
+    db="SNDBaseISap",
+    user=getenv('SNDB_USER'),
+    pwd=getenv('SNDB_PWD'),
+)
+
+csv_path = path.join(path.dirname(path.realpath(__file__)), "..", "server", "batches.csv")
+with open(csv_path, newline='') as f:
+    batches = [
+        [
+            row['id'],
+            row['mm'],
+            row['sheet_name'],
+            'Remnant' if row['remnant'] == 'Y' else 'New',
+        ]
+        for row in csv.DictReader(f)
+    ]
+
+with SndbConnection(**db_kwargs) as db:
+    for batch in batches:
+        db.execute(
+            """
+            IF NOT EXISTS (SELECT 1 FROM dbo.Batch WHERE BatchId=?)
+                INSERT INTO dbo.Batch (BatchId, MaterialMaster, SheetName, BatchType)
+                VALUES (?, ?, ?, ?)
+            """,
+            batch[0], *batch
+        )
+    db.commit()
+
+print("Imported {} batches".format(len(batches)))
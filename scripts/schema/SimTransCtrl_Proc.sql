@@ -6,8 +6,10 @@ USE SNDBaseISap;
 -- 	- dbo.Slab
 -- 	- dbo.SlabSheetAllocation
 -- 	- dbo.SlabPartAllocation
+-- 	- dbo.SapDemand
 -- 	- dbo.PushSapDemand
 -- 	- dbo.PushSapInventory
+-- 	- dbo.PushSapBatch
 --	- dbo.DeleteUnusedFeedback
 -- 	- dbo.GetProgramFeedback
 -- 	- dbo.GetPartFeedback
@@ -34,38 +36,56 @@ VALUES
 	('PRD', 2, '\\hssieng\SNDataPrd\RemSaveOutput\DXF'),
 	('DEV', 3, '\\hssieng\SNDataSbx\RemSaveOutput\DXF');
 GO
+-- A slab is nested in Sigmanest as a single sheet (`SheetName`), made up of
+-- 	physical sheets placed on it (SlabSheetAllocation).
+-- Sheets and parts allocated to a slab are held back from SAP inventory and
+-- 	demand until the slab program is completed (dbo.UpdateProgram).
 CREATE TABLE Slab(
-	SlabId INT PRIMARY KEY	
+	SlabId INT PRIMARY KEY,
+
+	-- Sigmanest sheet the slab is nested on
+	SheetName VARCHAR(50) NULL
 );
 GO
 CREATE TABLE SlabParts(
-	Id INT PRIMARY KEY,
+	Id INT IDENTITY(1,1) PRIMARY KEY,
 	SlabId INT FOREIGN KEY REFERENCES Slab(SlabId),
 	PartName VARCHAR(100),
 	Qty INT
 );
 GO
+-- Position of a physical sheet's origin corner on the slab.
+-- 	Length is along the X axis and width along the Y axis, before rotation.
 CREATE TABLE SlabSheetAllocation (
-	Id INT PRIMARY KEY,
+	Id INT IDENTITY(1,1) PRIMARY KEY,
 	SlabId INT FOREIGN KEY REFERENCES Slab(SlabId),
 	SheetIndex INT NOT NULL,
 	SheetName VARCHAR(50),
+	-- kept so reservations can be reported after the sheet leaves Sigmanest stock
+	MaterialMaster VARCHAR(50),
 	XPosition FLOAT,
 	YPosition FLOAT,
-	Rotation FLOAT,
+	Rotation FLOAT,	-- degrees, counterclockwise
 
 	-- TODO: other sheet fields
 	Width FLOAT,
-	Length FLOAT
+	Length FLOAT,
+
+	-- a physical sheet is placed on a slab at most once
+	CONSTRAINT UQ_SlabSheetAllocation_Sheet UNIQUE (SlabId, SheetName)
 );
 GO
+-- Position of a part on the slab, used to find the physical sheet it is cut from
 CREATE TABLE SlabPartAllocation (
-	Id INT PRIMARY KEY,
+	Id INT IDENTITY(1,1) PRIMARY KEY,
 	SlabId INT FOREIGN KEY REFERENCES Slab(SlabId),
 	SlabPartId INT FOREIGN KEY REFERENCES SlabParts(Id),
 	PartName VARCHAR(100),
 	WoNumber VARCHAR(50),
-	Qty INT
+	Qty INT,
+	XPosition FLOAT,
+	YPosition FLOAT,
+	Rotation FLOAT	-- degrees, counterclockwise
 	-- TODO: other part fields
 );
 GO
@@ -73,6 +93,41 @@ GO
 -- ********************************************
 -- *    Interface 1: Demand                   *
 -- ********************************************
+-- Sigmanest demand, comparable to SAP demand
+-- 	Qty: qty ordered, including any qty allocated to slabs (SAP's qty)
+-- 	QtyCommitted: qty completed or in process (PIP), which cannot be removed
+CREATE OR ALTER VIEW dbo.SapDemand
+AS
+	SELECT
+		_prt.Data18 AS SapEventId,
+		_prt.WONumber,
+		_prt.PartName,
+		_prt.QtyOrdered + COALESCE((
+			SELECT SUM(Qty)
+			FROM dbo.SlabPartAllocation AS _slab
+			WHERE _slab.PartName = _prt.PartName
+			AND _slab.WoNumber = _prt.WONumber
+		), 0) AS Qty,
+		_prt.QtyCompleted + COALESCE((
+			SELECT SUM(QtyInProcess)
+			FROM dbo.PIP AS _pip
+			WHERE _pip.PartName = _prt.PartName
+			AND _pip.WONumber = _prt.WONumber
+		), 0) AS QtyCommitted,
+		_prt.Material,
+		_prt.Customer,
+		_prt.DwgNumber,
+		_prt.Remark,
+		_prt.Data1,
+		_prt.Data2,
+		_prt.Data5,
+		_prt.Data6,
+		_prt.Data7,
+		_prt.Data8,
+		_prt.Data9,
+		_prt.Data10
+	FROM dbo.Part AS _prt;
+GO
 CREATE OR ALTER PROCEDURE dbo.PushSapDemand
 	@sap_system VARCHAR(3),
 	@sap_event_id VARCHAR(50) NULL,	-- SAP: numeric 20 positions, no decimal
@@ -114,20 +169,12 @@ BEGIN
 		-- This ensures that any demand in Sigmanest that is not in SAP is
 		--	removed since SAP will not always tell us that the demand was removed.
 		WITH _parts AS (
-			SELECT
-				PartName,
-				WONumber,
-				QtyCompleted + (
-					SELECT COALESCE(SUM(QtyInProcess), 0)
-					FROM dbo.PIP AS _pip
-					WHERE _prt.PartName = _pip.PartName
-					AND   _prt.WONumber = _pip.WONumber
-				) AS QtyCommited
-			FROM dbo.Part AS _prt
+			SELECT PartName, WONumber, QtyCommitted
+			FROM dbo.SapDemand
 			WHERE PartName = @part_name
 			-- keeps transactions from being inserted if the SimTrans runs in the 
 			--	middle of a data push.
-			AND Data18 != @sap_event_id
+			AND SapEventId != @sap_event_id
 		),
 		_cfg AS (
 			SELECT TOP 1 SimTransDistrict
@@ -145,7 +192,7 @@ BEGIN
 		)
 		SELECT
 			CASE
-				WHEN _parts.QtyCommited = 0
+				WHEN _parts.QtyCommitted = 0
 					THEN 'SN82'	-- Delete part from work order
 					ELSE 'SN81'	-- Modify part in work order
 			END,
@@ -153,13 +200,13 @@ BEGIN
 			@trans_id,
 			_parts.WONumber,
 			_parts.PartName,
-			_parts.QtyCommited,
+			_parts.QtyCommitted,
 			@sap_event_id
 		FROM _parts, _cfg;
 	END;
 
 	-- reduce @qty for any parts allocated for slabs
-	SELECT @qty = @qty - SUM(Qty)
+	SELECT @qty = @qty - COALESCE(SUM(Qty), 0)
 	FROM dbo.SlabPartAllocation
 	WHERE PartName = @part_name
 	AND WoNumber = @work_order;
@@ -252,7 +299,8 @@ CREATE OR ALTER PROCEDURE dbo.PushSapInventory
 	@notes1 VARCHAR(50) NULL,
 	@notes2 VARCHAR(50) NULL,
 	@notes3 VARCHAR(50) NULL,
-	@notes4 VARCHAR(50) NULL
+	@notes4 VARCHAR(50) NULL,
+	@batch VARCHAR(50) = NULL	-- SAP batch number
 AS
 SET NOCOUNT ON
 BEGIN
@@ -261,11 +309,21 @@ BEGIN
 	-- 	so truncating it to the 10 least significant digits is OK.
 	DECLARE @trans_id VARCHAR(10) = RIGHT(@sap_event_id, 10)
 
+	-- qty in SAP, before any reduction for slab allocations
+	DECLARE @sap_qty INT = @qty
+
 	-- Pre-event processing for any group of calls for the same @sap_event_id
 	-- Because of this `IF` statement, this will only do anything on the first
 	-- 	call for a given SAP event id (which should be for one material master).
 	IF @trans_id NOT IN (SELECT DISTINCT TransID FROM TransAct)
 	BEGIN
+		-- [0] Preemtively retire all batches for the given @mm, for the same
+		-- 	reason as [1]. Batches still in SAP are brought back by [4].
+		UPDATE dbo.Batch
+		SET Retired = 1, Updated = SYSDATETIME()
+		WHERE MaterialMaster = @mm
+		AND Retired = 0;
+
 		-- [1] Preemtively set all sheets to be removed for the given @mm
 		-- (excluding any sheets that are part of active nests). This makes
 		-- sure any sheets in Sigmanest that are not in SAP are removed since
@@ -387,6 +445,57 @@ BEGIN
 			END
 		FROM _cfg
 	END;
+
+	-- [4] Add/Update/Retire the SAP batch
+	EXEC dbo.PushSapBatch
+		@batch=@batch,
+		@sheet_name=@sheet_name,
+		@sheet_type=@sheet_type,
+		@qty=@sap_qty,
+		@mm=@mm;
+END;
+GO
+CREATE OR ALTER PROCEDURE dbo.PushSapBatch
+	@batch VARCHAR(50) NULL,	-- SAP batch number
+	@sheet_name VARCHAR(50) NULL,
+	@sheet_type VARCHAR(64),
+	@qty INT,
+	@mm VARCHAR(50)
+AS
+SET NOCOUNT ON
+BEGIN
+	-- Inventory without a batch number is not assignable to programs
+	IF @batch IS NULL
+		RETURN;
+
+	-- Batch is used up, so it can no longer be assigned
+	IF @qty <= 0 OR @sheet_name IS NULL
+	BEGIN
+		UPDATE dbo.Batch
+		SET Retired = 1, Updated = SYSDATETIME()
+		WHERE BatchId = @batch
+		AND Retired = 0;
+
+		RETURN;
+	END;
+
+	IF EXISTS (SELECT 1 FROM dbo.Batch WHERE BatchId = @batch)
+		UPDATE dbo.Batch
+		SET
+			MaterialMaster = @mm,
+			SheetName = @sheet_name,
+			BatchType = CASE @sheet_type WHEN 'Remnant' THEN 'Remnant' ELSE 'New' END,
+			Retired = 0,
+			Updated = SYSDATETIME()
+		WHERE BatchId = @batch;
+	ELSE
+		INSERT INTO dbo.Batch (BatchId, MaterialMaster, SheetName, BatchType)
+		VALUES (
+			@batch,
+			@mm,
+			@sheet_name,
+			CASE @sheet_type WHEN 'Remnant' THEN 'Remnant' ELSE 'New' END
+		);
 END;
 GO
 
@@ -426,7 +535,9 @@ END;
 GO
 CREATE OR ALTER PROCEDURE dbo.GetPartFeedback
 AS
-	-- TODO: handle slabs
+	-- Parts nested on a slab are reported for the slab's sheet.
+	-- 	The interface splits them per physical sheet by the slab's part
+	-- 	allocations (`/feedback/slab/{id}`).
 	SELECT
 		_pip.AutoID,
 		_pip.ArchivePacketID,
@@ -501,6 +612,8 @@ CREATE OR ALTER PROCEDURE dbo.UpdateProgram
 	@archive_packet_id INT
 AS
 SET NOCOUNT ON
+-- releasing the slab allocations and pushing SN76 succeed or fail together
+SET XACT_ABORT ON
 BEGIN
 	-- Expected Condition:
 	-- 	It is expected that the program with the given ArchivePacketID exists.
@@ -512,11 +625,33 @@ BEGIN
 	-- 	so truncating it to the 10 least significant digits is OK.
 	DECLARE @trans_id VARCHAR(10) = RIGHT(@sap_event_id, 10)
 
-	-- TODO: handle slabs
-	--	- remove allocations
-	--	- update or delete slab programs?
+	BEGIN TRAN;
+
+	-- [1] Release slab allocations
+	-- Sheets and parts of a completed slab are consumed, so they no longer
+	-- 	need to be held back from SAP inventory and demand.
+	WITH _slab AS (
+		SELECT DISTINCT Slab.SlabId
+		FROM dbo.Program
+		INNER JOIN dbo.SIP
+			ON  SIP.ProgramName = Program.ProgramName
+			AND SIP.RepeatID = Program.RepeatID
+		INNER JOIN dbo.Slab
+			ON Slab.SheetName = SIP.SheetName
+		WHERE Program.ArchivePacketID = @archive_packet_id
+	)
+	SELECT SlabId INTO #released_slabs FROM _slab;
+
+	DELETE FROM dbo.SlabPartAllocation
+	WHERE SlabId IN (SELECT SlabId FROM #released_slabs);
+	DELETE FROM dbo.SlabParts
+	WHERE SlabId IN (SELECT SlabId FROM #released_slabs);
+	DELETE FROM dbo.SlabSheetAllocation
+	WHERE SlabId IN (SELECT SlabId FROM #released_slabs);
 
-	-- [1] Update program
+	DROP TABLE #released_slabs;
+
+	-- [2] Update program
 	WITH _program AS (
 		SELECT
 			ProgramName,
@@ -542,6 +677,8 @@ BEGIN
 		@trans_id,
 		_program.ProgramName,
 		_program.RepeatId
-	FROM _program, _cfg
+	FROM _program, _cfg;
+
+	COMMIT;
 END;
 GO
\ No newline at end of file
@@ -1,6 +1,6 @@
 -- <update>
 insert into TransAct(TransType,District,ProgramName,ProgramRepeat)
 values
-    ('SN70',1,?,1)
+    ('SN70',1,?,?)
 
 --end
\ No newline at end of file
@@ -0,0 +1,87 @@
+-- Purpose: Create tables used by the operator interface server
+USE SNDBaseISap;
+
+-- ********************************************
+-- *    Program execution log                 *
+-- ********************************************
+-- One row per state change of a program repeat, so the latest row
+-- 	for a ProgramName/RepeatID is its current state.
+CREATE TABLE dbo.ProgramExecution (
+	Id INT IDENTITY(1,1) PRIMARY KEY,
+	ProgramName VARCHAR(50) NOT NULL,
+	RepeatID INT NOT NULL,
+
+	-- Initiated, Processing, Complete or Cancelled
+	State VARCHAR(16) NOT NULL,
+	Batch VARCHAR(50) NULL,
+	Operator VARCHAR(50) NULL,
+	Timestamp DATETIME2 NOT NULL DEFAULT SYSDATETIME()
+);
+CREATE INDEX IX_ProgramExecution_Program
+	ON dbo.ProgramExecution (ProgramName, RepeatID);
+GO
+
+-- ********************************************
+-- *    Batch registry                        *
+-- ********************************************
+-- SAP batches available to assign to programs.
+-- Batches are retired instead of deleted so completed programs keep their batch.
+CREATE TABLE dbo.Batch (
+	BatchId VARCHAR(50) PRIMARY KEY,
+	MaterialMaster VARCHAR(50) NOT NULL,
+	SheetName VARCHAR(50) NOT NULL,
+
+	-- New or Remnant
+	BatchType VARCHAR(16) NOT NULL,
+
+	-- Actual dimensions of non-standard (batch level inventory) batches,
+	-- 	used instead of the sheet's dimensions when the batch is assigned
+	ActualWidth FLOAT NULL,
+	ActualLength FLOAT NULL,
+
+	Retired BIT NOT NULL DEFAULT 0,
+	Updated DATETIME2 NOT NULL DEFAULT SYSDATETIME()
+);
+GO
+
+-- ********************************************
+-- *    Program batch assignment              *
+-- ********************************************
+-- Batch consumed by a program repeat, reported with completion feedback to SAP.
+-- Removed if the program goes back to Initiated or is Cancelled.
+-- A batch is consumed by at most one program repeat.
+CREATE TABLE dbo.ProgramBatch (
+	ArchivePacketID INT PRIMARY KEY,
+	ProgramName VARCHAR(50) NOT NULL,
+	RepeatID INT NOT NULL,
+	BatchId VARCHAR(50) NOT NULL FOREIGN KEY REFERENCES dbo.Batch(BatchId),
+	Assigned DATETIME2 NOT NULL DEFAULT SYSDATETIME()
+);
+CREATE UNIQUE INDEX IX_ProgramBatch_Batch
+	ON dbo.ProgramBatch (BatchId);
+GO
+
+-- ********************************************
+-- *    Machine registry                      *
+-- ********************************************
+-- Capabilities and status of machines, used to hide programs for machines
+-- 	that are down and to check alternate machines for a program.
+-- Machines that are not registered are treated as up with no known capabilities.
+CREATE TABLE dbo.Machine (
+	MachineName VARCHAR(50) PRIMARY KEY,
+
+	-- cutting process (i.e. Plasma, Oxyfuel)
+	Process VARCHAR(32) NULL,
+	MaxWidth FLOAT NULL,
+	MaxLength FLOAT NULL,
+	MinThickness FLOAT NULL,
+	MaxThickness FLOAT NULL,
+
+	-- Up, Down or Maintenance
+	Status VARCHAR(16) NOT NULL DEFAULT 'Up',
+
+	-- NC queue folder, used instead of the queue template in sndb.toml
+	NcQueue VARCHAR(260) NULL,
+	Updated DATETIME2 NOT NULL DEFAULT SYSDATETIME()
+);
+GO
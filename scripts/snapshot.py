@@ -6,7 +6,7 @@ from datetime import datetime
 import json
 
 st_tables = ["STPrgArc", "STPIPArc", "STPIPRejectedArchive", "STPrtArc", "STRemArc", "STShtArc", "STWOArc"]
-other_tables = ["Program", "Stock", "Part", "Remnant"]
+other_tables = ["Program", "PIP", "Stock", "Part", "Remnant"]
 
 db_kwargs = dict(
     server="HIISQLSERV6",
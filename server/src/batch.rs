@@ -1,28 +1,863 @@
-use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    db::{
+        api::{ProgramSheet, Sheet},
+        row::SqlRow,
+        store::SigmanestStore,
+        SqlConn,
+    },
+    extract::nullable,
+    Error, Result,
+};
+
+/// How long a loaded batch list is used before it is reloaded
+///
+/// Batches can be changed outside of this server, so the list is not kept forever.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all(serialize = "camelCase"))]
+#[serde(rename_all = "camelCase")]
 pub struct Batch {
     pub id: String,
     pub mm: String,
     pub sheet_name: String,
-    #[serde(rename(deserialize = "remnant"))]
     pub r#type: BatchType,
+    /// Actual dimensions of a non-standard batch, if they differ from the sheet
+    #[serde(default)]
+    pub actual_width: Option<f64>,
+    #[serde(default)]
+    pub actual_length: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchType {
+    New,
+    Remnant,
+}
+
+impl BatchType {
+    /// value stored in `Batch.BatchType`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "New",
+            Self::Remnant => "Remnant",
+        }
+    }
+}
+
+impl TryFrom<&str> for BatchType {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, String> {
+        match value {
+            "New" => Ok(Self::New),
+            "Remnant" => Ok(Self::Remnant),
+            other => Err(format!("`{}` is not a batch type", other)),
+        }
+    }
+}
+
+/// Changes to an existing batch, fields that are not given are left as is
+///
+/// Actual dimensions set to `null` clear the override.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchUpdate {
+    pub mm: Option<String>,
+    pub sheet_name: Option<String>,
+    pub r#type: Option<BatchType>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub actual_width: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub actual_length: Option<Option<f64>>,
+}
+
+/// A row of the non-standard batch list, as exported (i.e. `odd_batches.md`)
+///
+/// Dimensions are text since the export can have blank or invalid values,
+/// but numbers are accepted as well.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDimensionsRow {
+    pub batch: String,
+    pub sheet_name: String,
+    #[serde(default)]
+    pub batch_level_inventory: Option<String>,
+    #[serde(default, deserialize_with = "dimension_text")]
+    pub actual_width: String,
+    #[serde(default, deserialize_with = "dimension_text")]
+    pub actual_length: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedBatchRow {
+    pub batch: String,
+    pub reason: String,
+}
+
+/// Result of loading non-standard batch dimensions
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDimensionsReport {
+    /// batches updated with their actual dimensions
+    pub updated: Vec<Batch>,
+    pub rejected: Vec<RejectedBatchRow>,
 }
 
 impl Batch {
-    pub fn get_batches() -> crate::Result<Vec<Self>> {
-        csv::Reader::from_path("batches.csv")?
-            .into_deserialize::<Batch>()
-            .map(|r| r.map_err(crate::Error::from))
-            .collect()
+    /// get all batches that are not retired
+    pub async fn get_batches(conn: &mut SqlConn<'_>) -> Result<Vec<Self>> {
+        conn.simple_query(
+            r#"
+select BatchId, MaterialMaster, SheetName, BatchType, ActualWidth, ActualLength
+from Batch
+where Retired=0
+order by BatchId;
+            "#,
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
+    }
+
+    /// get a batch that is not retired
+    pub async fn get(conn: &mut SqlConn<'_>, id: &str) -> Result<Self> {
+        conn.query(
+            r#"
+select BatchId, MaterialMaster, SheetName, BatchType, ActualWidth, ActualLength
+from Batch
+where BatchId=@P1 and Retired=0;
+            "#,
+            &[&id],
+        )
+        .await?
+        .into_row()
+        .await?
+        .as_ref()
+        .map(Self::try_from)
+        .unwrap_or_else(|| Err(Error::NotFound(format!("Batch {} not found", id))))
+    }
+
+    /// adds a batch, or brings back a retired batch with the same id
+    pub async fn create(&self, conn: &mut SqlConn<'_>) -> Result<()> {
+        self.validate(conn).await?;
+
+        let result = conn
+            .execute(
+                r#"
+update Batch
+set
+	MaterialMaster=@P2, SheetName=@P3, BatchType=@P4,
+	ActualWidth=@P5, ActualLength=@P6,
+	Retired=0, Updated=SYSDATETIME()
+where BatchId=@P1 and Retired=1;
+if @@ROWCOUNT = 0 and not exists (select 1 from Batch where BatchId=@P1)
+	insert into Batch(BatchId, MaterialMaster, SheetName, BatchType, ActualWidth, ActualLength)
+	values (@P1, @P2, @P3, @P4, @P5, @P6);
+                "#,
+                &[
+                    &self.id.as_str(),
+                    &self.mm.as_str(),
+                    &self.sheet_name.as_str(),
+                    &self.r#type.as_str(),
+                    &self.actual_width,
+                    &self.actual_length,
+                ],
+            )
+            .await?;
+
+        match result.total() {
+            0 => Err(Error::Conflict(format!("Batch {} already exists", self.id))),
+            _ => Ok(()),
+        }
+    }
+
+    /// updates a batch that is not retired
+    pub async fn update(conn: &mut SqlConn<'_>, id: &str, update: BatchUpdate) -> Result<Self> {
+        let batch = Self::get(conn, id).await?.apply(update);
+        batch.validate(conn).await?;
+
+        conn.execute(
+            r#"
+update Batch
+set
+	MaterialMaster=@P2, SheetName=@P3, BatchType=@P4,
+	ActualWidth=@P5, ActualLength=@P6,
+	Updated=SYSDATETIME()
+where BatchId=@P1;
+            "#,
+            &[
+                &batch.id.as_str(),
+                &batch.mm.as_str(),
+                &batch.sheet_name.as_str(),
+                &batch.r#type.as_str(),
+                &batch.actual_width,
+                &batch.actual_length,
+            ],
+        )
+        .await?;
+
+        Ok(batch)
+    }
+
+    /// retires a batch so it can no longer be assigned
+    pub async fn retire(conn: &mut SqlConn<'_>, id: &str) -> Result<()> {
+        let result = conn
+            .execute(
+                "update Batch set Retired=1, Updated=SYSDATETIME() where BatchId=@P1 and Retired=0",
+                &[&id],
+            )
+            .await?;
+
+        match result.total() {
+            0 => Err(Error::NotFound(format!("Batch {} not found", id))),
+            _ => Ok(()),
+        }
+    }
+
+    /// sets the actual dimensions of non-standard batches
+    ///
+    /// Rows that cannot be applied are reported, instead of failing the whole list.
+    pub async fn load_dimensions(
+        conn: &mut SqlConn<'_>,
+        rows: Vec<BatchDimensionsRow>,
+    ) -> Result<BatchDimensionsReport> {
+        let mut report = BatchDimensionsReport::default();
+
+        for row in rows {
+            let result = Self::load_dimensions_row(conn, &row).await;
+            report.add(row.batch, result)?;
+        }
+
+        Ok(report)
+    }
+
+    async fn load_dimensions_row(conn: &mut SqlConn<'_>, row: &BatchDimensionsRow) -> Result<Self> {
+        let update = row.to_update()?;
+        let batch = Self::get(conn, &row.batch).await?;
+        row.check_sheet(&batch)?;
+
+        Self::update(conn, &row.batch, update).await
+    }
+
+    /// get the batches that can be used for a program repeat, best match first
+    pub async fn get_candidates(
+        conn: &mut SqlConn<'_>,
+        batches: Vec<Self>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<BatchCandidate>> {
+        let sheets = Sheet::get_by_program(conn, program, repeat_id).await?;
+        if sheets.is_empty() {
+            return Err(Error::NotFound(format!(
+                "No sheets found for program {} repeat {}",
+                program, repeat_id
+            )));
+        }
+
+        Ok(match_program_sheets(batches, &sheets))
+    }
+
+    /// checks the batch can be used to process a program repeat
+    ///
+    /// The batch has to be one of the program's candidates, and not be
+    /// consumed by another program that is processing or complete.
+    /// [`Batch::assign`] checks the consumer again when it is assigned.
+    pub async fn check_assignable(
+        conn: &mut SqlConn<'_>,
+        id: &str,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Self> {
+        let batches = Self::get_batches(conn).await?;
+        let candidate = Self::get_candidates(conn, batches, program, repeat_id)
+            .await?
+            .into_iter()
+            .find(|candidate| candidate.batch.id == id)
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Batch {} cannot be used for program {} repeat {}",
+                    id, program, repeat_id
+                ))
+            })?;
+        Self::check_consumer(conn, id, program, repeat_id).await?;
+
+        Ok(candidate.batch)
+    }
+
+    /// links the batch to the program repeat that consumes it
+    ///
+    /// Run in a [`Transaction`](crate::db::Transaction): the batch's consumer
+    /// is locked until it ends, so two programs cannot both take the batch.
+    /// `ProgramBatch.BatchId` is unique as well, in case it is assigned
+    /// outside of this server.
+    pub async fn assign(
+        &self,
+        conn: &mut SqlConn<'_>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<()> {
+        log::info!(
+            "Assigning batch {} to program {} repeat {}",
+            self.id,
+            program,
+            repeat_id
+        );
+
+        Self::check_consumer(conn, &self.id, program, repeat_id).await?;
+
+        conn.execute(
+            r#"
+delete from ProgramBatch where ProgramName=@P2 and RepeatID=@P3;
+insert into ProgramBatch(ArchivePacketID, ProgramName, RepeatID, BatchId)
+select ArchivePacketID, ProgramName, RepeatID, @P1
+from Program
+where ProgramName=@P2 and RepeatID=@P3;
+            "#,
+            &[&self.id.as_str(), &program, &repeat_id],
+        )
+        .await
+        .map_err(|e| {
+            Error::from(e)
+                .on_duplicate(|| format!("Batch {} is already used by another program", self.id))
+        })?;
+
+        Ok(())
+    }
+
+    /// checks the batch is not consumed by another program repeat, locking
+    /// its consumer until the end of the transaction
+    async fn check_consumer(
+        conn: &mut SqlConn<'_>,
+        id: &str,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<()> {
+        let consumer = conn
+            .query(
+                r#"
+select top 1 ProgramName, RepeatID
+from ProgramBatch with (updlock, holdlock)
+where BatchId=@P1
+and not (ProgramName=@P2 and RepeatID=@P3);
+                "#,
+                &[&id, &program, &repeat_id],
+            )
+            .await?
+            .into_row()
+            .await?;
+
+        match consumer {
+            Some(row) => {
+                let row = SqlRow::new(&row, "ProgramBatch");
+
+                Err(Error::Conflict(format!(
+                    "Batch {} is already used by program {} repeat {}",
+                    id,
+                    row.get::<&str>("ProgramName")?,
+                    row.get::<i32>("RepeatID")?
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// frees the batch linked to a program repeat, if any
+    pub async fn release(conn: &mut SqlConn<'_>, program: &str, repeat_id: i32) -> Result<()> {
+        conn.execute(
+            "delete from ProgramBatch where ProgramName=@P1 and RepeatID=@P2",
+            &[&program, &repeat_id],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// pushes the actual dimensions of a non-standard batch to its sheet
+    ///
+    /// Dimensions without an override keep the sheet's current value.
+    /// Remnants are pushed as `SN97` with their DXF file, using the district
+    /// and file template of `sap_system` in `SapInterfaceConfig`.
+    pub async fn push_dimensions(&self, conn: &mut SqlConn<'_>, sap_system: &str) -> Result<()> {
+        if !self.has_override() {
+            return Ok(());
+        }
+
+        if self.is_standard() {
+            log::warn!(
+                "Not updating standard sheet {} to actual dimensions of batch {}",
+                self.sheet_name,
+                self.id
+            );
+            return Ok(());
+        }
+
+        log::info!(
+            "Updating sheet {} to actual dimensions of batch {}",
+            self.sheet_name,
+            self.id
+        );
+
+        let update = conn
+            .execute(
+                r#"
+with _cfg as (
+	select top 1
+		RemnantDxfTemplate,
+		SimTransDistrict
+	from SapInterfaceConfig
+	where SapSystem=@P1
+)
+insert into TransAct(
+	TransType, District,
+	ItemName, Qty, Material, Thickness, Width, Length, PrimeCode, BinNumber,
+	FileName
+)
+select
+	case @P3 when 'Remnant' then 'SN97' else 'SN91A' end,
+	_cfg.SimTransDistrict,
+	SheetName, Qty, Material, Thickness,
+	coalesce(@P4, Width), coalesce(@P5, Length),
+	PrimeCode, BinNumber,
+	case @P3
+		when 'Remnant' then replace(_cfg.RemnantDxfTemplate, '<sheet_name>', SheetName)
+		else null
+	end
+from Stock, _cfg
+where SheetName=@P2;
+                "#,
+                &[
+                    &sap_system,
+                    &self.sheet_name.as_str(),
+                    &self.r#type.as_str(),
+                    &self.actual_width,
+                    &self.actual_length,
+                ],
+            )
+            .await?;
+
+        match update.rows_affected().iter().sum::<u64>() {
+            0 => Err(Error::NotFound(format!(
+                "Sheet {} of batch {} not found, or SAP system {} is not configured",
+                self.sheet_name, self.id, sap_system
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// checks the batch has an id and its material master is in Sigmanest stock
+    async fn validate(&self, conn: &mut SqlConn<'_>) -> Result<()> {
+        self.check()?;
+
+        let stock = conn
+            .query(
+                "select top 1 PrimeCode from Stock where PrimeCode=@P1",
+                &[&self.mm.as_str()],
+            )
+            .await?
+            .into_row()
+            .await?;
+
+        match stock {
+            Some(_) => Ok(()),
+            None => Err(Error::Validation(format!(
+                "Material master {} of batch {} is not in Stock",
+                self.mm, self.id
+            ))),
+        }
+    }
+
+    /// applies changes to the batch
+    pub(crate) fn apply(self, update: BatchUpdate) -> Self {
+        Self {
+            id: self.id,
+            mm: update.mm.unwrap_or(self.mm),
+            sheet_name: update.sheet_name.unwrap_or(self.sheet_name),
+            r#type: update.r#type.unwrap_or(self.r#type),
+            actual_width: update.actual_width.unwrap_or(self.actual_width),
+            actual_length: update.actual_length.unwrap_or(self.actual_length),
+        }
+    }
+
+    /// standard batches are for sheets named by their material master
+    pub fn is_standard(&self) -> bool {
+        self.sheet_name == self.mm
+    }
+
+    /// checks if the batch has actual dimensions that differ from its sheet
+    pub fn has_override(&self) -> bool {
+        self.actual_width.is_some() || self.actual_length.is_some()
+    }
+
+    /// checks the batch has an id and its actual dimensions are positive
+    ///
+    /// Only non-standard batches can have actual dimensions, since a
+    /// standard sheet is shared by all batches of its material master.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.id.trim().is_empty() {
+            return Err(Error::Validation(String::from("batch id is required")));
+        }
+
+        if self.is_standard() && self.has_override() {
+            return Err(Error::Validation(format!(
+                "Batch {} is for standard sheet {}, only non-standard batches have actual dimensions",
+                self.id, self.sheet_name
+            )));
+        }
+
+        for (name, value) in [("width", self.actual_width), ("length", self.actual_length)] {
+            if value.is_some_and(|val| !val.is_finite() || val <= 0.0) {
+                return Err(Error::Validation(format!(
+                    "Actual {} of batch {} must be a positive number",
+                    name, self.id
+                )));
+            }
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum BatchType {
-    #[serde(rename(deserialize = "N"))]
-    New,
-    #[serde(rename(deserialize = "Y"))]
-    Remnant,
+impl TryFrom<&tiberius::Row> for Batch {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let row = SqlRow::new(row, "Batch");
+
+        Ok(Self {
+            id: row.get_string("BatchId")?,
+            mm: row.get_string("MaterialMaster")?,
+            sheet_name: row.get_string("SheetName")?,
+            r#type: BatchType::try_from(row.get::<&str>("BatchType")?)
+                .map_err(|e| row.invalid("BatchType", &e))?,
+            actual_width: row.get_opt("ActualWidth")?,
+            actual_length: row.get_opt("ActualLength")?,
+        })
+    }
+}
+
+/// How well a batch matches the sheets of a program, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum BatchMatch {
+    /// batch is for the sheet the program is nested on
+    Sheet,
+    /// batch is for the same material master as a standard sheet
+    MaterialMaster,
+    /// batch is for the same material master, but the program is nested on a singleton sheet
+    SingletonMaterialMaster,
+}
+
+/// A batch that qualifies for a program, with why it qualified
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchCandidate {
+    #[serde(flatten)]
+    pub batch: Batch,
+    #[serde(rename = "match")]
+    pub r#match: BatchMatch,
+    pub reasons: Vec<String>,
+}
+
+/// ranks the batches that can be used for a program nested on `sheets`
+///
+/// A program on combined sheets has several sheets, and a batch can qualify for any of them.
+/// Remnants are single pieces, so only batches for the remnant itself qualify.
+pub fn match_program_sheets(batches: Vec<Batch>, sheets: &[ProgramSheet]) -> Vec<BatchCandidate> {
+    let combined = sheets.len() > 1;
+
+    let mut candidates: Vec<BatchCandidate> = batches
+        .into_iter()
+        .filter_map(|batch| {
+            let mut best = None;
+            let mut reasons = Vec::new();
+
+            for sheet in sheets {
+                let sheet_name = &sheet.sheet.sheet_name;
+                let same_mm = batch.mm == sheet.sheet.material_master;
+                let same_type = sheet.is_remnant == (batch.r#type == BatchType::Remnant);
+
+                let (level, reason) = if &batch.sheet_name == sheet_name && same_type {
+                    (BatchMatch::Sheet, format!("batch is for sheet {}", sheet_name))
+                } else if !same_mm || !same_type || sheet.is_remnant {
+                    continue;
+                } else if sheet.is_standard() {
+                    (
+                        BatchMatch::MaterialMaster,
+                        format!("material master {} matches sheet {}", batch.mm, sheet_name),
+                    )
+                } else {
+                    (
+                        BatchMatch::SingletonMaterialMaster,
+                        format!(
+                            "material master {} matches, but program is nested on singleton sheet {}",
+                            batch.mm, sheet_name
+                        ),
+                    )
+                };
+
+                best = Some(best.map_or(level, |b: BatchMatch| b.min(level)));
+                reasons.push(match combined {
+                    true => format!("{} (combined with {} other sheets)", reason, sheets.len() - 1),
+                    false => reason,
+                });
+            }
+
+            best.map(|r#match| BatchCandidate {
+                batch,
+                r#match,
+                reasons,
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        a.r#match
+            .cmp(&b.r#match)
+            .then_with(|| a.batch.id.cmp(&b.batch.id))
+    });
+    candidates
+}
+
+impl BatchDimensionsRow {
+    /// parses the row into the dimensions to set on its batch
+    pub(crate) fn to_update(&self) -> Result<BatchUpdate> {
+        if let Some(level) = self.batch_level_inventory.as_deref() {
+            if !level.trim().eq_ignore_ascii_case("Y") {
+                return Err(Error::Validation(String::from(
+                    "batch is not batch level inventory",
+                )));
+            }
+        }
+
+        let width = parse_dimension("width", &self.actual_width)?;
+        let length = parse_dimension("length", &self.actual_length)?;
+        if width.is_none() && length.is_none() {
+            return Err(Error::Validation(String::from(
+                "no actual width or length given",
+            )));
+        }
+
+        Ok(BatchUpdate {
+            actual_width: width.map(Some),
+            actual_length: length.map(Some),
+            ..Default::default()
+        })
+    }
+
+    /// checks the row is for the sheet of its batch
+    pub(crate) fn check_sheet(&self, batch: &Batch) -> Result<()> {
+        match batch.sheet_name == self.sheet_name.trim() {
+            true => Ok(()),
+            false => Err(Error::Validation(format!(
+                "batch is for sheet {}, not {}",
+                batch.sheet_name, self.sheet_name
+            ))),
+        }
+    }
+}
+
+impl BatchDimensionsReport {
+    /// adds the result of loading a row, rejecting it if it cannot be applied
+    pub(crate) fn add(&mut self, batch: String, result: Result<Batch>) -> Result<()> {
+        match result {
+            Ok(batch) => self.updated.push(batch),
+            Err(Error::NotFound(reason) | Error::Validation(reason)) => {
+                log::warn!("Rejected dimensions of batch {}: {}", batch, reason);
+                self.rejected.push(RejectedBatchRow { batch, reason });
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+}
+
+/// parses an exported dimension, where blank means no override
+fn parse_dimension(name: &str, value: &str) -> Result<Option<f64>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match value.parse::<f64>() {
+        Ok(val) if val.is_finite() && val > 0.0 => Ok(Some(val)),
+        _ => Err(Error::Validation(format!(
+            "actual {} `{}` is not a positive number",
+            name, value
+        ))),
+    }
+}
+
+/// reads an exported dimension, given as text or a number
+fn dimension_text<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Dimension {
+        Number(f64),
+        Text(String),
+    }
+
+    Ok(match Option::<Dimension>::deserialize(deserializer)? {
+        Some(Dimension::Number(val)) => val.to_string(),
+        Some(Dimension::Text(val)) => val,
+        None => String::new(),
+    })
+}
+
+/// Batch list shared between requests
+///
+/// Changes made through this server invalidate the list, and it is
+/// reloaded after [`CACHE_MAX_AGE`] to pick up changes made elsewhere.
+#[derive(Debug, Default)]
+pub struct BatchCache {
+    batches: Mutex<Option<(Instant, Vec<Batch>)>>,
+}
+
+impl BatchCache {
+    /// get the batch list, loading it if needed
+    pub async fn get(&self, store: &dyn SigmanestStore) -> Result<Vec<Batch>> {
+        let mut cache = self.batches.lock().await;
+
+        match cache.as_ref() {
+            Some((loaded, batches)) if loaded.elapsed() < CACHE_MAX_AGE => Ok(batches.clone()),
+            _ => {
+                let batches = store.get_batches().await?;
+                *cache = Some((Instant::now(), batches.clone()));
+
+                Ok(batches)
+            }
+        }
+    }
+
+    /// drops the batch list so the next request reloads it
+    pub async fn invalidate(&self) {
+        *self.batches.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(id: &str, mm: &str, sheet_name: &str, r#type: BatchType) -> Batch {
+        Batch {
+            id: String::from(id),
+            mm: String::from(mm),
+            sheet_name: String::from(sheet_name),
+            r#type,
+            actual_width: None,
+            actual_length: None,
+        }
+    }
+
+    fn sheet(sheet_name: &str, mm: &str, is_remnant: bool) -> ProgramSheet {
+        ProgramSheet {
+            sheet: Sheet {
+                sheet_name: String::from(sheet_name),
+                material_master: String::from(mm),
+            },
+            is_remnant,
+        }
+    }
+
+    fn matches(candidates: &[BatchCandidate]) -> Vec<(&str, BatchMatch)> {
+        candidates
+            .iter()
+            .map(|candidate| (candidate.batch.id.as_str(), candidate.r#match))
+            .collect()
+    }
+
+    #[test]
+    fn standard_sheet() {
+        let batches = vec![
+            batch("B3", "50W-0500", "S-0001", BatchType::New),
+            batch("B2", "50W-0500", "50W-0500", BatchType::New),
+            batch("B1", "50W-0375", "50W-0375", BatchType::New),
+            batch("B0", "50W-0500", "R-0001", BatchType::Remnant),
+        ];
+
+        let candidates = match_program_sheets(batches, &[sheet("50W-0500", "50W-0500", false)]);
+        assert_eq!(
+            matches(&candidates),
+            [
+                ("B2", BatchMatch::Sheet),
+                ("B3", BatchMatch::MaterialMaster)
+            ]
+        );
+    }
+
+    #[test]
+    fn singleton_sheet() {
+        let batches = vec![
+            batch("B1", "50W-0500", "S-0001", BatchType::New),
+            batch("B2", "50W-0500", "50W-0500", BatchType::New),
+        ];
+
+        let candidates = match_program_sheets(batches, &[sheet("S-0001", "50W-0500", false)]);
+        assert_eq!(
+            matches(&candidates),
+            [
+                ("B1", BatchMatch::Sheet),
+                ("B2", BatchMatch::SingletonMaterialMaster)
+            ]
+        );
+    }
+
+    #[test]
+    fn remnant_sheet() {
+        // only a remnant batch for the remnant itself qualifies
+        let batches = vec![
+            batch("B1", "50W-0375", "R-0001", BatchType::Remnant),
+            batch("B2", "50W-0375", "R-0002", BatchType::Remnant),
+            batch("B3", "50W-0375", "50W-0375", BatchType::New),
+            batch("B4", "50W-0375", "R-0001", BatchType::New),
+        ];
+
+        let candidates = match_program_sheets(batches, &[sheet("R-0001", "50W-0375", true)]);
+        assert_eq!(matches(&candidates), [("B1", BatchMatch::Sheet)]);
+    }
+
+    #[test]
+    fn combined_sheets() {
+        let batches = vec![
+            batch("B1", "50W-0500", "S-0001", BatchType::New),
+            batch("B2", "50W-0375", "50W-0375", BatchType::New),
+        ];
+        let sheets = [
+            sheet("50W-0500", "50W-0500", false),
+            sheet("S-0001", "50W-0500", false),
+            sheet("50W-0375", "50W-0375", false),
+        ];
+
+        // the best match of any sheet is used, with the reasons of all sheets
+        let candidates = match_program_sheets(batches, &sheets);
+        assert_eq!(
+            matches(&candidates),
+            [("B1", BatchMatch::Sheet), ("B2", BatchMatch::Sheet)]
+        );
+        assert_eq!(
+            candidates[0].reasons,
+            [
+                "material master 50W-0500 matches sheet 50W-0500 (combined with 2 other sheets)",
+                "batch is for sheet S-0001 (combined with 2 other sheets)",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_sheets() {
+        let batches = vec![batch("B1", "50W-0500", "50W-0500", BatchType::New)];
+
+        assert!(match_program_sheets(batches, &[]).is_empty());
+    }
 }
@@ -0,0 +1,364 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{row::SqlRow, SqlConn},
+    Error, Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgramState {
+    Initiated,
+    Processing,
+    Complete,
+    Cancelled,
+}
+
+impl ProgramState {
+    /// value stored in `ProgramExecution.State`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Initiated => "Initiated",
+            Self::Processing => "Processing",
+            Self::Complete => "Complete",
+            Self::Cancelled => "Cancelled",
+        }
+    }
+
+    /// checks if a program repeat in state `previous` can move to this state
+    ///
+    /// A repeat starts (or restarts after being cancelled) as `Initiated`.
+    /// Operators can go back from `Processing` to `Initiated` to pick a
+    /// different batch, and `Complete` is final.
+    pub fn can_follow(&self, previous: Option<Self>) -> bool {
+        use ProgramState::*;
+
+        matches!(
+            (previous, self),
+            (None | Some(Initiated | Cancelled), Initiated)
+                | (Some(Initiated), Processing)
+                | (Some(Processing), Initiated | Complete)
+                | (Some(Initiated | Processing), Cancelled)
+        )
+    }
+}
+
+impl TryFrom<&str> for ProgramState {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, String> {
+        match value {
+            "Initiated" => Ok(Self::Initiated),
+            "Processing" => Ok(Self::Processing),
+            "Complete" => Ok(Self::Complete),
+            "Cancelled" => Ok(Self::Cancelled),
+            state => Err(format!("`{}` is not a program state", state)),
+        }
+    }
+}
+
+/// A recorded state change of a program repeat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramExecution {
+    pub id: i32,
+    pub program_name: String,
+    pub repeat_id: i32,
+    pub state: ProgramState,
+    pub batch: Option<String>,
+    pub operator: Option<String>,
+    /// ISO 8601 time the state was recorded
+    pub timestamp: String,
+}
+
+impl ProgramExecution {
+    /// get all recorded state changes of a program, oldest first
+    pub async fn get_history(conn: &mut SqlConn<'_>, program: &str) -> Result<Vec<Self>> {
+        conn.query(
+            r#"
+select
+	Id, ProgramName, RepeatID, State, Batch, Operator,
+	convert(varchar(33), Timestamp, 126) as Timestamp
+from ProgramExecution
+where ProgramName=@P1
+order by Id;
+            "#,
+            &[&program],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
+    }
+
+    /// get the current state of a program repeat, if it has one
+    pub async fn get_latest(
+        conn: &mut SqlConn<'_>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Option<Self>> {
+        conn.query(
+            r#"
+select top 1
+	Id, ProgramName, RepeatID, State, Batch, Operator,
+	convert(varchar(33), Timestamp, 126) as Timestamp
+from ProgramExecution
+where ProgramName=@P1 and RepeatID=@P2
+order by Id desc;
+            "#,
+            &[&program, &repeat_id],
+        )
+        .await?
+        .into_row()
+        .await?
+        .as_ref()
+        .map(Self::try_from)
+        .transpose()
+    }
+
+    /// get the repeat of a program that is being worked on, or the next
+    /// repeat that can be started
+    pub async fn current_repeat(conn: &mut SqlConn<'_>, program: &str) -> Result<i32> {
+        let row = conn
+            .query(
+                r#"
+select top 1
+	Program.RepeatID
+from Program
+outer apply (
+	select top 1 State
+	from ProgramExecution
+	where ProgramExecution.ProgramName=Program.ProgramName
+	and ProgramExecution.RepeatID=Program.RepeatID
+	order by Id desc
+) as _exec
+where ProgramName=@P1
+and not exists (
+	select 1
+	from TransAct
+	where TransType='SN70'
+	and TransAct.ProgramName=Program.ProgramName
+	and TransAct.ProgramRepeat=Program.RepeatID
+)
+order by
+	case when _exec.State in ('Initiated', 'Processing') then 0 else 1 end,
+	Program.RepeatID;
+                "#,
+                &[&program],
+            )
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| {
+                Error::NotFound(format!("Program {} has no repeats left to run", program))
+            })?;
+
+        SqlRow::new(&row, "Program").get("RepeatID")
+    }
+
+    /// get the repeats of a program that are processing
+    ///
+    /// In a transaction, the program's state changes are locked until it ends.
+    pub async fn processing_repeats(conn: &mut SqlConn<'_>, program: &str) -> Result<Vec<i32>> {
+        conn.query(
+            r#"
+select _exec.RepeatID
+from ProgramExecution as _exec with (updlock, holdlock)
+where ProgramName=@P1
+and State='Processing'
+and Id=(
+	select max(Id)
+	from ProgramExecution
+	where ProgramName=_exec.ProgramName and RepeatID=_exec.RepeatID
+);
+            "#,
+            &[&program],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(|row| SqlRow::new(row, "ProgramExecution").get("RepeatID"))
+        .collect()
+    }
+
+    /// checks if any other repeat of the program is processing
+    ///
+    /// Repeats of a program share its NC file.
+    pub async fn other_repeats_processing(
+        conn: &mut SqlConn<'_>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<bool> {
+        let repeats = Self::processing_repeats(conn, program).await?;
+
+        Ok(repeats.into_iter().any(|repeat| repeat != repeat_id))
+    }
+
+    async fn repeat_exists(conn: &mut SqlConn<'_>, program: &str, repeat_id: i32) -> Result<bool> {
+        let row = conn
+            .query(
+                "select 1 from Program where ProgramName=@P1 and RepeatID=@P2",
+                &[&program, &repeat_id],
+            )
+            .await?
+            .into_row()
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// records a state change of a program repeat
+    ///
+    /// Run in a [`Transaction`](crate::db::Transaction): the current state is
+    /// read and locked until it ends, so concurrent requests for a repeat are
+    /// applied one at a time and cannot both complete it. Completing a repeat
+    /// also issues its SimTrans `SN70` update, so the log and SimTrans cannot
+    /// disagree.
+    pub async fn transition(
+        conn: &mut SqlConn<'_>,
+        sap_system: &str,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<Self> {
+        Self::check_transition(conn, program, repeat_id, state).await?;
+
+        Self::record(conn, sap_system, program, repeat_id, state, batch, operator).await
+    }
+
+    /// checks the repeat can move to `state`, locking its state changes
+    /// until the end of the transaction
+    async fn check_transition(
+        conn: &mut SqlConn<'_>,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+    ) -> Result<()> {
+        let previous = conn
+            .query(
+                r#"
+select top 1 State
+from ProgramExecution with (updlock, holdlock)
+where ProgramName=@P1 and RepeatID=@P2
+order by Id desc;
+                "#,
+                &[&program, &repeat_id],
+            )
+            .await?
+            .into_row()
+            .await?;
+        let previous = match previous {
+            Some(row) => {
+                let row = SqlRow::new(&row, "ProgramExecution");
+                let previous = ProgramState::try_from(row.get::<&str>("State")?)
+                    .map_err(|e| row.invalid("State", &e))?;
+
+                Some(previous)
+            }
+            None => None,
+        };
+
+        if state == ProgramState::Initiated
+            && !Self::repeat_exists(conn, program, repeat_id).await?
+        {
+            return Err(Error::NotFound(format!(
+                "Program {} repeat {} not found",
+                program, repeat_id
+            )));
+        }
+
+        if !state.can_follow(previous) {
+            return Err(Error::Conflict(format!(
+                "Program {} repeat {} cannot move from {} to {}",
+                program,
+                repeat_id,
+                previous.map(|s| s.as_str()).unwrap_or("not started"),
+                state.as_str()
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn record(
+        conn: &mut SqlConn<'_>,
+        sap_system: &str,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<Self> {
+        if state == ProgramState::Complete {
+            let completed = conn
+                .execute(
+                    r#"
+with _cfg as (
+	select top 1 SimTransDistrict
+	from SapInterfaceConfig
+	where SapSystem=@P1
+)
+insert into TransAct(TransType,District,ProgramName,ProgramRepeat)
+select 'SN70', _cfg.SimTransDistrict, @P2, @P3
+from _cfg;
+                    "#,
+                    &[&sap_system, &program, &repeat_id],
+                )
+                .await?;
+
+            if completed.rows_affected().iter().sum::<u64>() == 0 {
+                return Err(Error::Config(format!(
+                    "SAP system {} is not configured in SapInterfaceConfig",
+                    sap_system
+                )));
+            }
+        }
+
+        let row = conn
+            .query(
+                r#"
+insert into ProgramExecution(ProgramName, RepeatID, State, Batch, Operator)
+output
+	inserted.Id, inserted.ProgramName, inserted.RepeatID,
+	inserted.State, inserted.Batch, inserted.Operator,
+	convert(varchar(33), inserted.Timestamp, 126) as Timestamp
+values (@P1, @P2, @P3, @P4, @P5);
+                "#,
+                &[&program, &repeat_id, &state.as_str(), &batch, &operator],
+            )
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| {
+                Error::Conflict(format!(
+                    "State of program {} repeat {} was not recorded",
+                    program, repeat_id
+                ))
+            })?;
+
+        Self::try_from(&row)
+    }
+}
+
+impl TryFrom<&tiberius::Row> for ProgramExecution {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let row = SqlRow::new(row, "ProgramExecution");
+
+        Ok(Self {
+            id: row.get("Id")?,
+            program_name: row.get_string("ProgramName")?,
+            repeat_id: row.get("RepeatID")?,
+            state: ProgramState::try_from(row.get::<&str>("State")?)
+                .map_err(|e| row.invalid("State", &e))?,
+            batch: row.get_opt_string("Batch")?,
+            operator: row.get_opt_string("Operator")?,
+            timestamp: row.get_string("Timestamp")?,
+        })
+    }
+}
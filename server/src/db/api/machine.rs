@@ -0,0 +1,514 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ProgramExecution, Sheet, SheetSize};
+use crate::{
+    db::{row::SqlRow, SqlConn, Transaction},
+    extract::nullable,
+    Error, Result,
+};
+
+/// A machine that programs are nested for
+///
+/// Machines that are not in the `Machine` registry have no known
+/// capabilities and are treated as up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Machine {
+    pub name: String,
+    /// cutting process (i.e. Plasma, Oxyfuel)
+    pub process: Option<String>,
+    pub max_width: Option<f64>,
+    pub max_length: Option<f64>,
+    pub min_thickness: Option<f64>,
+    pub max_thickness: Option<f64>,
+    pub status: MachineStatus,
+    /// NC queue folder, used instead of the configured queue template
+    pub nc_queue: Option<String>,
+    /// machine is in the registry
+    pub registered: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MachineStatus {
+    #[default]
+    Up,
+    Down,
+    Maintenance,
+}
+
+impl MachineStatus {
+    /// value stored in `Machine.Status`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Maintenance => "Maintenance",
+        }
+    }
+
+    /// checks if programs can be run on the machine
+    pub fn is_available(&self) -> bool {
+        *self == Self::Up
+    }
+}
+
+impl TryFrom<&str> for MachineStatus {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, String> {
+        match value {
+            "Up" => Ok(Self::Up),
+            "Down" => Ok(Self::Down),
+            "Maintenance" => Ok(Self::Maintenance),
+            status => Err(format!("`{}` is not a machine status", status)),
+        }
+    }
+}
+
+/// Changes to a machine's registry entry, fields that are not given are left as is
+///
+/// Fields set to `null` are cleared.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineUpdate {
+    #[serde(default, deserialize_with = "nullable")]
+    pub process: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub max_width: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub max_length: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub min_thickness: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub max_thickness: Option<Option<f64>>,
+    pub status: Option<MachineStatus>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub nc_queue: Option<Option<String>>,
+}
+
+/// A program with repeats left to run on a machine
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramSummary {
+    pub program: String,
+    pub cutting_time: f64,
+    /// number of repeats that are not complete
+    pub repeats: i32,
+}
+
+impl Machine {
+    /// get all machines that have programs or are in the registry
+    pub async fn get_all(conn: &mut SqlConn<'_>) -> Result<Vec<Self>> {
+        conn.simple_query(
+            r#"
+select
+	_machines.MachineName,
+	Process, MaxWidth, MaxLength, MinThickness, MaxThickness,
+	coalesce(Status, 'Up') as Status, NcQueue,
+	case when Machine.MachineName is null then 0 else 1 end as Registered
+from (
+	select MachineName from ProgramMachine
+	union
+	select MachineName from Machine
+) as _machines
+left join Machine on Machine.MachineName=_machines.MachineName
+order by _machines.MachineName;
+            "#,
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
+    }
+
+    /// get a machine that has programs or is in the registry
+    pub async fn get(conn: &mut SqlConn<'_>, name: &str) -> Result<Self> {
+        conn.query(
+            r#"
+select
+	_machines.MachineName,
+	Process, MaxWidth, MaxLength, MinThickness, MaxThickness,
+	coalesce(Status, 'Up') as Status, NcQueue,
+	case when Machine.MachineName is null then 0 else 1 end as Registered
+from (
+	select MachineName from ProgramMachine
+	union
+	select MachineName from Machine
+) as _machines
+left join Machine on Machine.MachineName=_machines.MachineName
+where _machines.MachineName=@P1;
+            "#,
+            &[&name],
+        )
+        .await?
+        .into_row()
+        .await?
+        .as_ref()
+        .map(Self::try_from)
+        .unwrap_or_else(|| Err(Error::NotFound(format!("Machine {} not found", name))))
+    }
+
+    /// get the NC queue folder of a machine, if it has one in the registry
+    pub async fn get_nc_queue(conn: &mut SqlConn<'_>, name: &str) -> Result<Option<String>> {
+        match Self::get(conn, name).await {
+            Ok(machine) => Ok(machine.nc_queue),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// adds a machine to the registry, or updates its registry entry
+    pub async fn update(conn: &mut SqlConn<'_>, name: &str, update: MachineUpdate) -> Result<Self> {
+        let mut tx = Transaction::begin(conn).await?;
+        let result = Self::upsert(&mut tx, name, update).await;
+
+        tx.end(result).await
+    }
+
+    async fn upsert(conn: &mut SqlConn<'_>, name: &str, update: MachineUpdate) -> Result<Self> {
+        // hold the registry entry, or the gap where it would be inserted,
+        // so concurrent updates are applied one after the other
+        conn.execute(
+            "select MachineName from Machine with (updlock, holdlock) where MachineName=@P1;",
+            &[&name],
+        )
+        .await?;
+
+        let current = match Self::get(conn, name).await {
+            Ok(machine) => machine,
+            Err(Error::NotFound(_)) => Self::unregistered(name),
+            Err(e) => return Err(e),
+        };
+        let machine = current.apply(update);
+        machine.validate()?;
+
+        conn.execute(
+            r#"
+update Machine
+set
+	Process=@P2, MaxWidth=@P3, MaxLength=@P4,
+	MinThickness=@P5, MaxThickness=@P6,
+	Status=@P7, NcQueue=@P8,
+	Updated=SYSDATETIME()
+where MachineName=@P1;
+if @@ROWCOUNT = 0
+	insert into Machine(
+		MachineName, Process, MaxWidth, MaxLength,
+		MinThickness, MaxThickness, Status, NcQueue
+	)
+	values (@P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8);
+            "#,
+            &[
+                &machine.name.as_str(),
+                &machine.process.as_deref(),
+                &machine.max_width,
+                &machine.max_length,
+                &machine.min_thickness,
+                &machine.max_thickness,
+                &machine.status.as_str(),
+                &machine.nc_queue.as_deref(),
+            ],
+        )
+        .await?;
+
+        Ok(machine)
+    }
+
+    /// applies changes to the machine's registry entry
+    pub(crate) fn apply(self, update: MachineUpdate) -> Self {
+        Self {
+            name: self.name,
+            process: update.process.unwrap_or(self.process),
+            max_width: update.max_width.unwrap_or(self.max_width),
+            max_length: update.max_length.unwrap_or(self.max_length),
+            min_thickness: update.min_thickness.unwrap_or(self.min_thickness),
+            max_thickness: update.max_thickness.unwrap_or(self.max_thickness),
+            status: update.status.unwrap_or(self.status),
+            nc_queue: update.nc_queue.unwrap_or(self.nc_queue),
+            registered: true,
+        }
+    }
+
+    pub(crate) fn unregistered(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            process: None,
+            max_width: None,
+            max_length: None,
+            min_thickness: None,
+            max_thickness: None,
+            status: MachineStatus::default(),
+            nc_queue: None,
+            registered: false,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        let limits = [
+            ("max width", self.max_width),
+            ("max length", self.max_length),
+            ("min thickness", self.min_thickness),
+            ("max thickness", self.max_thickness),
+        ];
+        for (name, value) in limits {
+            if let Some(value) = value {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(Error::Validation(format!(
+                        "{} of machine {} must be a positive number",
+                        name, self.name
+                    )));
+                }
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_thickness, self.max_thickness) {
+            if min > max {
+                return Err(Error::Validation(format!(
+                    "min thickness of machine {} is more than its max thickness",
+                    self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// checks if the machine can cut a sheet, giving the reason if it cannot
+    ///
+    /// Sheets can be rotated to fit, and limits that are not set are not checked.
+    pub fn check_sheet(&self, sheet: &SheetSize) -> std::result::Result<(), String> {
+        let fits = |width: f64, length: f64| {
+            self.max_width.is_none_or(|max| width <= max)
+                && self.max_length.is_none_or(|max| length <= max)
+        };
+        if !fits(sheet.width, sheet.length) && !fits(sheet.length, sheet.width) {
+            return Err(format!(
+                "sheet {} ({} x {}) does not fit on machine {}",
+                sheet.sheet_name, sheet.width, sheet.length, self.name
+            ));
+        }
+
+        let too_thin = self.min_thickness.is_some_and(|min| sheet.thickness < min);
+        let too_thick = self.max_thickness.is_some_and(|max| sheet.thickness > max);
+        if too_thin || too_thick {
+            return Err(format!(
+                "sheet {} thickness {} is outside the range of machine {}",
+                sheet.sheet_name, sheet.thickness, self.name
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// get the machines a program can be moved to
+    ///
+    /// Alternate machines have to be registered, up, use the same process as
+    /// the program's current machine and be able to cut all of its sheets.
+    pub async fn get_alternates(conn: &mut SqlConn<'_>, program: &str) -> Result<Vec<Self>> {
+        let current = Self::get_by_program(conn, program).await?;
+        let sheets = Sheet::get_sizes_by_program(conn, program).await?;
+
+        let alternates = Self::get_all(conn)
+            .await?
+            .into_iter()
+            .filter(|machine| machine.check_alternate(&current, &sheets).is_ok())
+            .collect();
+
+        Ok(alternates)
+    }
+
+    /// moves all repeats of a program to another machine
+    ///
+    /// Not allowed while a repeat is processing, since its NC file is
+    /// staged to the current machine's queue. The program's state changes
+    /// are locked while it is moved, so none of its repeats can start
+    /// processing on the old machine in the meantime.
+    pub async fn reassign(conn: &mut SqlConn<'_>, program: &str, machine: &str) -> Result<Self> {
+        let mut tx = Transaction::begin(conn).await?;
+        let result = Self::move_program(&mut tx, program, machine).await;
+
+        tx.end(result).await
+    }
+
+    async fn move_program(conn: &mut SqlConn<'_>, program: &str, machine: &str) -> Result<Self> {
+        let current = Self::get_by_program(conn, program).await?;
+        let target = Self::get(conn, machine).await?;
+        let sheets = Sheet::get_sizes_by_program(conn, program).await?;
+
+        target
+            .check_alternate(&current, &sheets)
+            .map_err(|reason| {
+                Error::Validation(format!(
+                    "Program {} cannot be moved to machine {}: {}",
+                    program, machine, reason
+                ))
+            })?;
+
+        if !ProgramExecution::processing_repeats(conn, program)
+            .await?
+            .is_empty()
+        {
+            return Err(Error::Conflict(format!(
+                "Program {} cannot be moved while it is processing",
+                program
+            )));
+        }
+
+        log::info!(
+            "Moving program {} from machine {} to {}",
+            program,
+            current.name,
+            target.name
+        );
+
+        conn.execute(
+            r#"
+update Program set MachineName=@P2 where ProgramName=@P1;
+update ProgramMachine set MachineName=@P2 where ProgramName=@P1;
+            "#,
+            &[&program, &target.name.as_str()],
+        )
+        .await?;
+
+        Ok(target)
+    }
+
+    async fn get_by_program(conn: &mut SqlConn<'_>, program: &str) -> Result<Self> {
+        let row = conn
+            .query(
+                "select top 1 MachineName from Program where ProgramName=@P1",
+                &[&program],
+            )
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("Program {} not found", program)))?;
+        let name = SqlRow::new(&row, "Program").get_string("MachineName")?;
+
+        match Self::get(conn, &name).await {
+            Err(Error::NotFound(_)) => Ok(Self::unregistered(&name)),
+            machine => machine,
+        }
+    }
+
+    /// checks if a program can be moved from `current` to this machine
+    pub(crate) fn check_alternate(
+        &self,
+        current: &Self,
+        sheets: &[SheetSize],
+    ) -> std::result::Result<(), String> {
+        if self.name == current.name {
+            return Err(String::from("program is already on this machine"));
+        }
+        if !self.registered {
+            return Err(String::from("machine is not registered"));
+        }
+        if !self.status.is_available() {
+            return Err(format!("machine is {}", self.status.as_str()));
+        }
+        if let Some(process) = &current.process {
+            match &self.process {
+                Some(own) if own == process => (),
+                Some(own) => {
+                    return Err(format!(
+                        "machine process {} does not match {}",
+                        own, process
+                    ))
+                }
+                None => return Err(format!("machine has no process, program needs {}", process)),
+            }
+        }
+
+        sheets.iter().try_for_each(|sheet| self.check_sheet(sheet))
+    }
+}
+
+impl ProgramSummary {
+    /// get programs on a machine that have repeats left to run
+    ///
+    /// With `hide_unavailable`, machines that are down have no programs listed.
+    pub async fn get_by_machine(
+        conn: &mut SqlConn<'_>,
+        machine: &str,
+        hide_unavailable: bool,
+    ) -> Result<Vec<Self>> {
+        if hide_unavailable {
+            match Machine::get(conn, machine).await {
+                Ok(machine) if !machine.status.is_available() => return Ok(Vec::new()),
+                Ok(_) | Err(Error::NotFound(_)) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        conn.query(
+            r#"
+select distinct
+	ProgramName,
+	CuttingTime,
+	rpt.Repeats
+from ProgramMachine
+inner join (
+	select
+		ProgramName as p,
+		count(RepeatID) as Repeats
+	from Program
+	where not exists (
+		select 1
+		from TransAct
+		where TransType = 'SN70'
+		and TransAct.ProgramName=Program.ProgramName
+		and TransAct.ProgramRepeat=Program.RepeatId
+	)
+	group by ProgramName
+) as rpt
+	on rpt.p=ProgramMachine.ProgramName
+where MachineName=@P1
+and rpt.Repeats > 0;
+            "#,
+            &[&machine],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
+    }
+}
+
+impl TryFrom<&tiberius::Row> for Machine {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let row = SqlRow::new(row, "Machine");
+
+        Ok(Self {
+            name: row.get_string("MachineName")?,
+            process: row.get_opt_string("Process")?,
+            max_width: row.get_opt("MaxWidth")?,
+            max_length: row.get_opt("MaxLength")?,
+            min_thickness: row.get_opt("MinThickness")?,
+            max_thickness: row.get_opt("MaxThickness")?,
+            status: MachineStatus::try_from(row.get::<&str>("Status")?)
+                .map_err(|e| row.invalid("Status", &e))?,
+            nc_queue: row.get_opt_string("NcQueue")?,
+            registered: row.get::<i32>("Registered")? == 1,
+        })
+    }
+}
+
+impl TryFrom<&tiberius::Row> for ProgramSummary {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let row = SqlRow::new(row, "ProgramMachine");
+
+        Ok(Self {
+            program: row.get_string("ProgramName")?,
+            cutting_time: row.get("CuttingTime")?,
+            repeats: row.get("Repeats")?,
+        })
+    }
+}
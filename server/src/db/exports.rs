@@ -1,26 +1,19 @@
-use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, oneshot};
 
 use super::{
-    api::{FeedbackEntry, Nest, Part, Remnant, TransactionType},
-    DbPool,
+    api::{FeedbackEntry, FeedbackReport, Nest, Part, Remnant, TransactionType},
+    DbPool, SqlConn, Transaction,
 };
-use crate::Result;
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct STArcEntry<T> {
-    archive_packet_id: i32,
-    state: TransactionType<T>,
-}
+use crate::{Error, Result};
 
 #[derive(Debug)]
 enum GetProgramData {
     GetParts(i32, String, oneshot::Sender<Result<Vec<Part>>>),
     GetRemnants(String, i32, oneshot::Sender<Result<Vec<Remnant>>>),
 }
-pub async fn export_feedback(db: DbPool) -> Result<Vec<FeedbackEntry<Nest>>> {
-    let mut programs: Vec<FeedbackEntry<Nest>> = db
+pub async fn export_feedback(db: DbPool) -> Result<FeedbackReport<FeedbackEntry<Nest>>> {
+    // nests that fail to map are skipped, so one bad program does not block all feedback
+    let rows = db
         .get()
         .await?
         .simple_query(
@@ -40,20 +33,29 @@ inner join Stock on Stock.SheetName=STPrgArc.SheetName;
         )
         .await?
         .into_first_result()
-        .await?
-        .iter()
-        .map::<Result<FeedbackEntry<Nest>>, _>(FeedbackEntry::try_from)
-        .collect::<Result<Vec<FeedbackEntry<Nest>>>>()?;
+        .await?;
+    let programs = FeedbackEntry::<Nest>::report(&rows, "STPrgArc")?;
 
     // db fetching actor
     // let mut conn = db.get().await?;
     let (get_parts, mut rx) = mpsc::channel(8);
     tokio::spawn(async move {
         while let Some(payload) = rx.recv().await {
-            let conn = &mut db
-                .get()
-                .await
-                .expect("Failed to get db connection in worker");
+            let conn = &mut match db.get().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log::error!("Failed to get db connection in worker: {:?}", e);
+                    match payload {
+                        GetProgramData::GetParts(_, _, respond_to) => {
+                            let _ = respond_to.send(Err(Error::SqlPoolError));
+                        }
+                        GetProgramData::GetRemnants(_, _, respond_to) => {
+                            let _ = respond_to.send(Err(Error::SqlPoolError));
+                        }
+                    }
+                    continue;
+                }
+            };
 
             match payload {
                 GetProgramData::GetParts(apid, tcode, respond_to) => {
@@ -69,9 +71,14 @@ inner join Stock on Stock.SheetName=STPrgArc.SheetName;
         }
     });
 
-    let mut nests = Vec::new();
-    while let Some(mut program) = programs.pop() {
-        if let TransactionType::Created(ref mut nest) = program.state {
+    let mut rows = FeedbackReport {
+        feedback: Vec::new(),
+        skipped: programs.skipped,
+    };
+    for program in programs.feedback {
+        let mut nest_rows = NestFeedbackRows::default();
+
+        if let TransactionType::Created(ref nest) = program.state {
             // get parts
             let (respond_to, parts) = oneshot::channel();
             let _ = get_parts
@@ -92,18 +99,94 @@ inner join Stock on Stock.SheetName=STPrgArc.SheetName;
                 ))
                 .await;
 
-            // store parts result
             if let Ok(parts) = parts.await {
-                nest.parts.append(&mut parts?);
+                nest_rows.parts = parts;
             }
-            // store remnants result
             if let Ok(rems) = rems.await {
-                nest.remnants.append(&mut rems?);
+                nest_rows.remnants = rems;
             }
         }
 
-        nests.push(program);
+        rows.feedback.push((program, nest_rows));
+    }
+
+    feedback_from_rows(rows)
+}
+
+/// Parts and remnants read for a posted nest by [`export_feedback`]
+#[derive(Debug)]
+pub struct NestFeedbackRows {
+    pub parts: Result<Vec<Part>>,
+    pub remnants: Result<Vec<Remnant>>,
+}
+
+impl Default for NestFeedbackRows {
+    fn default() -> Self {
+        Self {
+            parts: Ok(Vec::new()),
+            remnants: Ok(Vec::new()),
+        }
     }
+}
+
+/// assembles exported feedback from program feedback rows, with the parts and
+/// remnants read for each posted nest
+///
+/// Feedback is exported last row first. Nests with parts or remnants that
+/// cannot be read are skipped, so one bad program does not block all feedback.
+pub fn feedback_from_rows(
+    rows: FeedbackReport<(FeedbackEntry<Nest>, NestFeedbackRows)>,
+) -> Result<FeedbackReport<FeedbackEntry<Nest>>> {
+    let mut report = FeedbackReport {
+        feedback: Vec::new(),
+        skipped: rows.skipped,
+    };
 
-    Ok(nests)
+    for (mut program, nest_rows) in rows.feedback.into_iter().rev() {
+        if let TransactionType::Created(ref mut nest) = program.state {
+            let loaded = nest_rows
+                .parts
+                .and_then(|parts| Ok((parts, nest_rows.remnants?)));
+
+            match loaded {
+                Ok((mut parts, mut remnants)) => {
+                    nest.parts.append(&mut parts);
+                    nest.remnants.append(&mut remnants);
+                }
+                Err(e) => {
+                    report.skip_nest(program.archive_packet_id, e)?;
+                    continue;
+                }
+            }
+        }
+
+        report.feedback.push(program);
+    }
+
+    Ok(report)
+}
+
+/// deletes the program and part feedback of an `ArchivePacketID`
+///
+/// Feedback is deleted once it has been exported, so it is not exported again.
+pub async fn delete_feedback(conn: &mut SqlConn<'_>, archive_packet_id: i32) -> Result<()> {
+    let mut tx = Transaction::begin(conn).await?;
+    let result = tx
+        .execute(
+            r#"
+delete from STPIPArc where ArchivePacketID=@P1;
+delete from STPrgArc where ArchivePacketID=@P1;
+            "#,
+            &[&archive_packet_id],
+        )
+        .await
+        .map_err(Error::from);
+
+    match tx.end(result).await?.rows_affected().iter().sum::<u64>() {
+        0 => Err(Error::NotFound(format!(
+            "No feedback with ArchivePacketID {}",
+            archive_packet_id
+        ))),
+        _ => Ok(()),
+    }
 }
@@ -1,5 +1,5 @@
-mod pool;
-pub use pool::*;
+pub use sndb::db::*;
 
 pub mod api;
 pub mod exports;
+pub mod store;
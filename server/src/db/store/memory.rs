@@ -0,0 +1,868 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+
+use super::{
+    snapshot::{ProgramRow, StockRow},
+    SigmanestStore, Snapshot,
+};
+use crate::{
+    batch::{
+        match_program_sheets, Batch, BatchCandidate, BatchDimensionsReport, BatchDimensionsRow,
+        BatchType, BatchUpdate,
+    },
+    db::{
+        api::{
+            FeedbackEntry, FeedbackReport, Machine, MachineUpdate, Nest, Program, ProgramExecution,
+            ProgramSheet, ProgramState, ProgramSummary, SheetSize,
+        },
+        exports::feedback_from_rows,
+    },
+    Error, Result,
+};
+
+/// `SapInterfaceConfig.RemnantDxfTemplate` used for remnant sheets
+pub const REMNANT_DXF_TEMPLATE: &str = r"\\hssieng\SNDataDev\RemSaveOutput\DXF\<sheet_name>.dxf";
+
+/// `SapInterfaceConfig.SimTransDistrict` of each SAP system
+pub const SIM_TRANS_DISTRICTS: [(&str, i32); 3] = [("QAS", 1), ("PRD", 2), ("DEV", 3)];
+
+/// A SimTrans transaction pushed to `TransAct`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimTransaction {
+    pub trans_type: String,
+    pub district: i32,
+    pub program_name: Option<String>,
+    pub program_repeat: Option<i32>,
+    pub item_name: Option<String>,
+    pub file_name: Option<String>,
+}
+
+/// Store that keeps Sigmanest data in memory
+///
+/// Sigmanest tables are seeded from a [`Snapshot`], and the interface's own
+/// tables (machine registry, batches and program executions) start empty.
+/// Nests and exported feedback are assembled from the snapshot's rows like
+/// the database's. Snapshots have no `SIP` or `ProgramMachine` tables, so the
+/// sheets matched to batches come from `Program.SheetName`.
+/// SimTrans transactions are recorded, but not applied to the Sigmanest tables.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+#[derive(Debug, Default)]
+struct Tables {
+    snapshot: Snapshot,
+    /// `SapInterfaceConfig` row SimTrans transactions are pushed for
+    sap_system: String,
+
+    machines: Vec<Machine>,
+    /// batches, with if they are retired
+    batches: Vec<(Batch, bool)>,
+    /// batches consumed by program repeats
+    program_batches: Vec<(String, i32, String)>,
+    executions: Vec<ProgramExecution>,
+    transactions: Vec<SimTransaction>,
+}
+
+impl MemoryStore {
+    pub fn new(snapshot: Snapshot) -> Self {
+        let tables = Tables {
+            snapshot,
+            sap_system: String::from("QAS"),
+            ..Default::default()
+        };
+
+        Self {
+            tables: Mutex::new(tables),
+        }
+    }
+
+    /// pushes SimTrans transactions for another SAP system than QAS
+    pub fn with_sap_system(self, sap_system: &str) -> Self {
+        self.tables().sap_system = String::from(sap_system);
+
+        self
+    }
+
+    /// loads a store from a snapshot file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Snapshot::load(path).map(Self::new)
+    }
+
+    /// get the SimTrans transactions pushed so far, oldest first
+    pub fn transactions(&self) -> Vec<SimTransaction> {
+        self.tables().transactions.clone()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Tables {
+    fn program(&self, program: &str, repeat_id: i32) -> Option<&ProgramRow> {
+        self.snapshot
+            .programs
+            .iter()
+            .find(|row| row.is(program, repeat_id))
+    }
+
+    fn program_repeats<'a>(&'a self, program: &'a str) -> impl Iterator<Item = &'a ProgramRow> {
+        self.snapshot
+            .programs
+            .iter()
+            .filter(move |row| row.program_name.as_deref() == Some(program))
+    }
+
+    fn stock(&self, sheet_name: &str) -> Option<&StockRow> {
+        self.snapshot
+            .stock
+            .iter()
+            .find(|row| row.sheet_name.as_deref() == Some(sheet_name))
+    }
+
+    /// checks if a program repeat has been completed in SimTrans
+    fn is_complete(&self, program: &str, repeat_id: i32) -> bool {
+        self.transactions.iter().any(|trans| {
+            trans.trans_type == "SN70"
+                && trans.program_name.as_deref() == Some(program)
+                && trans.program_repeat == Some(repeat_id)
+        })
+    }
+
+    /// get the SimTrans district of the store's SAP system
+    fn district(&self) -> Result<i32> {
+        SIM_TRANS_DISTRICTS
+            .iter()
+            .find(|(sap_system, _)| *sap_system == self.sap_system)
+            .map(|(_, district)| *district)
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "SAP system {} is not configured in SapInterfaceConfig",
+                    self.sap_system
+                ))
+            })
+    }
+
+    fn push_transaction(&mut self, transaction: SimTransaction) {
+        log::debug!("Pushed SimTrans transaction {:?}", transaction);
+
+        self.transactions.push(transaction);
+    }
+
+    fn machine(&self, name: &str) -> Result<Machine> {
+        if let Some(machine) = self.machines.iter().find(|machine| machine.name == name) {
+            return Ok(machine.clone());
+        }
+
+        match self
+            .snapshot
+            .programs
+            .iter()
+            .any(|row| row.machine_name.as_deref() == Some(name))
+        {
+            true => Ok(Machine::unregistered(name)),
+            false => Err(Error::NotFound(format!("Machine {} not found", name))),
+        }
+    }
+
+    fn machines(&self) -> Vec<Machine> {
+        let mut names: Vec<&str> = self
+            .snapshot
+            .programs
+            .iter()
+            .filter_map(|row| row.machine_name.as_deref())
+            .chain(self.machines.iter().map(|machine| machine.name.as_str()))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        names
+            .into_iter()
+            .filter_map(|name| self.machine(name).ok())
+            .collect()
+    }
+
+    fn machine_by_program(&self, program: &str) -> Result<Machine> {
+        let name = self
+            .program_repeats(program)
+            .next()
+            .and_then(|row| row.machine_name.as_deref())
+            .ok_or_else(|| Error::NotFound(format!("Program {} not found", program)))?;
+
+        match self.machine(name) {
+            Err(Error::NotFound(_)) => Ok(Machine::unregistered(name)),
+            machine => machine,
+        }
+    }
+
+    /// get the sizes of all sheets used by any repeat of a program
+    fn sheet_sizes(&self, program: &str) -> Result<Vec<SheetSize>> {
+        let mut sheet_names: Vec<&str> = self
+            .program_repeats(program)
+            .filter_map(|row| row.sheet_name.as_deref())
+            .collect();
+        sheet_names.sort_unstable();
+        sheet_names.dedup();
+
+        sheet_names
+            .into_iter()
+            .filter_map(|sheet_name| self.stock(sheet_name))
+            .map(StockRow::size)
+            .collect()
+    }
+
+    fn program_sheets(&self, program: &str, repeat_id: i32) -> Vec<ProgramSheet> {
+        self.program(program, repeat_id)
+            .and_then(|row| row.sheet_name.as_deref())
+            .and_then(|sheet_name| self.stock(sheet_name))
+            .map(|stock| ProgramSheet {
+                sheet: stock.sheet(),
+                is_remnant: stock.file_name.is_some()
+                    || self.snapshot.remnants.iter().any(|rem| {
+                        rem.remnant_name.is_some() && rem.remnant_name == stock.sheet_name
+                    }),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn nest(&self, program_name: &str, repeat_id: i32) -> Result<Nest> {
+        let rows = self.snapshot.nest_rows(program_name, repeat_id);
+
+        Nest::from_rows(program_name, repeat_id, rows)
+    }
+
+    fn latest_execution(&self, program: &str, repeat_id: i32) -> Option<&ProgramExecution> {
+        self.executions
+            .iter()
+            .rev()
+            .find(|exec| exec.program_name == program && exec.repeat_id == repeat_id)
+    }
+
+    fn processing_repeats(&self, program: &str) -> Vec<i32> {
+        self.program_repeats(program)
+            .filter_map(|row| row.repeat_id)
+            .filter(|&repeat_id| {
+                self.latest_execution(program, repeat_id)
+                    .is_some_and(|exec| exec.state == ProgramState::Processing)
+            })
+            .collect()
+    }
+
+    fn batch(&self, id: &str) -> Result<Batch> {
+        self.batches
+            .iter()
+            .find(|(batch, retired)| batch.id == id && !retired)
+            .map(|(batch, _)| batch.clone())
+            .ok_or_else(|| Error::NotFound(format!("Batch {} not found", id)))
+    }
+
+    fn active_batches(&self) -> Vec<Batch> {
+        let mut batches: Vec<Batch> = self
+            .batches
+            .iter()
+            .filter(|(_, retired)| !retired)
+            .map(|(batch, _)| batch.clone())
+            .collect();
+        batches.sort_by(|a, b| a.id.cmp(&b.id));
+
+        batches
+    }
+
+    /// checks the batch has an id and its material master is in Sigmanest stock
+    fn validate_batch(&self, batch: &Batch) -> Result<()> {
+        batch.check()?;
+
+        match self
+            .snapshot
+            .stock
+            .iter()
+            .any(|row| row.prime_code.as_deref() == Some(batch.mm.as_str()))
+        {
+            true => Ok(()),
+            false => Err(Error::Validation(format!(
+                "Material master {} of batch {} is not in Stock",
+                batch.mm, batch.id
+            ))),
+        }
+    }
+
+    fn update_batch(&mut self, id: &str, update: BatchUpdate) -> Result<Batch> {
+        let batch = self.batch(id)?.apply(update);
+        self.validate_batch(&batch)?;
+
+        if let Some((stored, _)) = self
+            .batches
+            .iter_mut()
+            .find(|(stored, retired)| stored.id == id && !retired)
+        {
+            *stored = batch.clone();
+        }
+
+        Ok(batch)
+    }
+
+    /// runs `f`, undoing its changes to program executions, batches and
+    /// SimTrans transactions if it fails, like a SQL transaction
+    fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let program_batches = self.program_batches.clone();
+        let executions = self.executions.len();
+        let transactions = self.transactions.len();
+
+        let result = f(self);
+        if result.is_err() {
+            self.program_batches = program_batches;
+            self.executions.truncate(executions);
+            self.transactions.truncate(transactions);
+        }
+
+        result
+    }
+
+    /// records a state change of a program repeat
+    fn transition(
+        &mut self,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<ProgramExecution> {
+        let previous = self
+            .latest_execution(program, repeat_id)
+            .map(|exec| exec.state);
+
+        if state == ProgramState::Initiated && self.program(program, repeat_id).is_none() {
+            return Err(Error::NotFound(format!(
+                "Program {} repeat {} not found",
+                program, repeat_id
+            )));
+        }
+
+        if !state.can_follow(previous) {
+            return Err(Error::Conflict(format!(
+                "Program {} repeat {} cannot move from {} to {}",
+                program,
+                repeat_id,
+                previous.map(|s| s.as_str()).unwrap_or("not started"),
+                state.as_str()
+            )));
+        }
+
+        if state == ProgramState::Complete {
+            let district = self.district()?;
+            self.push_transaction(SimTransaction {
+                trans_type: String::from("SN70"),
+                district,
+                program_name: Some(String::from(program)),
+                program_repeat: Some(repeat_id),
+                item_name: None,
+                file_name: None,
+            });
+        }
+
+        let execution = ProgramExecution {
+            id: self.executions.len() as i32 + 1,
+            program_name: String::from(program),
+            repeat_id,
+            state,
+            batch: batch.map(String::from),
+            operator: operator.map(String::from),
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+        };
+        self.executions.push(execution.clone());
+
+        Ok(execution)
+    }
+
+    /// checks the batch is not consumed by another program repeat
+    fn check_consumer(&self, id: &str, program: &str, repeat_id: i32) -> Result<()> {
+        let consumer = self
+            .program_batches
+            .iter()
+            .find(|(prg, rpt, batch)| batch == id && !(prg == program && *rpt == repeat_id));
+
+        match consumer {
+            Some((prg, rpt, _)) => Err(Error::Conflict(format!(
+                "Batch {} is already used by program {} repeat {}",
+                id, prg, rpt
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// links the batch to the program repeat that consumes it
+    fn assign_batch(&mut self, batch: &Batch, program: &str, repeat_id: i32) -> Result<()> {
+        log::info!(
+            "Assigning batch {} to program {} repeat {}",
+            batch.id,
+            program,
+            repeat_id
+        );
+
+        self.check_consumer(&batch.id, program, repeat_id)?;
+
+        self.release_batch(program, repeat_id);
+        if self.program(program, repeat_id).is_some() {
+            self.program_batches
+                .push((String::from(program), repeat_id, batch.id.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// frees the batch linked to a program repeat, if any
+    fn release_batch(&mut self, program: &str, repeat_id: i32) {
+        self.program_batches
+            .retain(|(prg, rpt, _)| !(prg == program && *rpt == repeat_id));
+    }
+
+    /// pushes the SimTrans transaction that updates the sheet of a
+    /// non-standard batch to its actual dimensions
+    fn push_batch_dimensions(&mut self, batch: &Batch) -> Result<()> {
+        if !batch.has_override() {
+            return Ok(());
+        }
+
+        if batch.is_standard() {
+            log::warn!(
+                "Not updating standard sheet {} to actual dimensions of batch {}",
+                batch.sheet_name,
+                batch.id
+            );
+            return Ok(());
+        }
+
+        log::info!(
+            "Updating sheet {} to actual dimensions of batch {}",
+            batch.sheet_name,
+            batch.id
+        );
+
+        if self.stock(&batch.sheet_name).is_none() {
+            return Err(Error::NotFound(format!(
+                "Sheet {} of batch {} not found",
+                batch.sheet_name, batch.id
+            )));
+        }
+
+        let (trans_type, file_name) = match batch.r#type {
+            BatchType::Remnant => (
+                "SN97",
+                Some(REMNANT_DXF_TEMPLATE.replace("<sheet_name>", &batch.sheet_name)),
+            ),
+            BatchType::New => ("SN91A", None),
+        };
+        let district = self.district()?;
+        self.push_transaction(SimTransaction {
+            trans_type: String::from(trans_type),
+            district,
+            program_name: None,
+            program_repeat: None,
+            item_name: Some(batch.sheet_name.clone()),
+            file_name,
+        });
+
+        Ok(())
+    }
+
+    fn batch_candidates(
+        &self,
+        batches: Vec<Batch>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<BatchCandidate>> {
+        let sheets = self.program_sheets(program, repeat_id);
+        if sheets.is_empty() {
+            return Err(Error::NotFound(format!(
+                "No sheets found for program {} repeat {}",
+                program, repeat_id
+            )));
+        }
+
+        Ok(match_program_sheets(batches, &sheets))
+    }
+}
+
+#[async_trait]
+impl SigmanestStore for MemoryStore {
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn get_machines(&self) -> Result<Vec<Machine>> {
+        Ok(self.tables().machines())
+    }
+
+    async fn get_machine(&self, name: &str) -> Result<Machine> {
+        self.tables().machine(name)
+    }
+
+    async fn update_machine(&self, name: &str, update: MachineUpdate) -> Result<Machine> {
+        let mut tables = self.tables();
+
+        let current = match tables.machine(name) {
+            Ok(machine) => machine,
+            Err(Error::NotFound(_)) => Machine::unregistered(name),
+            Err(e) => return Err(e),
+        };
+        let machine = current.apply(update);
+        machine.validate()?;
+
+        match tables.machines.iter_mut().find(|m| m.name == machine.name) {
+            Some(registered) => *registered = machine.clone(),
+            None => tables.machines.push(machine.clone()),
+        }
+
+        Ok(machine)
+    }
+
+    async fn get_nc_queue(&self, machine: &str) -> Result<Option<String>> {
+        match self.tables().machine(machine) {
+            Ok(machine) => Ok(machine.nc_queue),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_alternate_machines(&self, program: &str) -> Result<Vec<Machine>> {
+        let tables = self.tables();
+        let current = tables.machine_by_program(program)?;
+        let sheets = tables.sheet_sizes(program)?;
+
+        let alternates = tables
+            .machines()
+            .into_iter()
+            .filter(|machine| machine.check_alternate(&current, &sheets).is_ok())
+            .collect();
+
+        Ok(alternates)
+    }
+
+    async fn reassign_program(&self, program: &str, machine: &str) -> Result<Machine> {
+        let mut tables = self.tables();
+        let current = tables.machine_by_program(program)?;
+        let target = tables.machine(machine)?;
+        let sheets = tables.sheet_sizes(program)?;
+
+        target
+            .check_alternate(&current, &sheets)
+            .map_err(|reason| {
+                Error::Validation(format!(
+                    "Program {} cannot be moved to machine {}: {}",
+                    program, machine, reason
+                ))
+            })?;
+
+        if !tables.processing_repeats(program).is_empty() {
+            return Err(Error::Conflict(format!(
+                "Program {} cannot be moved while it is processing",
+                program
+            )));
+        }
+
+        log::info!(
+            "Moving program {} from machine {} to {}",
+            program,
+            current.name,
+            target.name
+        );
+
+        tables
+            .snapshot
+            .programs
+            .iter_mut()
+            .filter(|row| row.program_name.as_deref() == Some(program))
+            .for_each(|row| row.machine_name = Some(target.name.clone()));
+
+        Ok(target)
+    }
+
+    async fn get_programs(
+        &self,
+        machine: &str,
+        hide_unavailable: bool,
+    ) -> Result<Vec<ProgramSummary>> {
+        let tables = self.tables();
+
+        if hide_unavailable {
+            match tables.machine(machine) {
+                Ok(machine) if !machine.status.is_available() => return Ok(Vec::new()),
+                Ok(_) | Err(Error::NotFound(_)) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut programs: BTreeMap<&str, Vec<&ProgramRow>> = BTreeMap::new();
+        for row in &tables.snapshot.programs {
+            if let (Some(program), Some(machine_name)) = (&row.program_name, &row.machine_name) {
+                if machine_name == machine {
+                    programs.entry(program).or_default().push(row);
+                }
+            }
+        }
+
+        let mut summaries = Vec::new();
+        for (program, rows) in programs {
+            let repeats = rows
+                .iter()
+                .filter(|row| {
+                    row.repeat_id
+                        .is_some_and(|repeat_id| !tables.is_complete(program, repeat_id))
+                })
+                .count();
+            if repeats == 0 {
+                continue;
+            }
+
+            summaries.push(ProgramSummary {
+                program: String::from(program),
+                cutting_time: rows[0].program()?.cutting_time,
+                repeats: repeats as i32,
+            });
+        }
+
+        Ok(summaries)
+    }
+
+    async fn get_program(&self, program: &str, repeat_id: i32) -> Result<Program> {
+        self.tables()
+            .program(program, repeat_id)
+            .map(ProgramRow::program)
+            .unwrap_or_else(|| {
+                Err(Error::NotFound(format!(
+                    "Program {} repeat {} not found",
+                    program, repeat_id
+                )))
+            })
+    }
+
+    async fn get_nest(&self, program: &str, repeat_id: i32) -> Result<Nest> {
+        self.tables().nest(program, repeat_id)
+    }
+
+    async fn get_nest_by_archive_packet_id(&self, archive_packet_id: i32) -> Result<Nest> {
+        let tables = self.tables();
+        let row = tables
+            .snapshot
+            .programs
+            .iter()
+            .find(|row| row.archive_packet_id == Some(archive_packet_id))
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "No program with ArchivePacketID {}",
+                    archive_packet_id
+                ))
+            })?;
+        let program = row.program()?;
+
+        tables.nest(&program.program_name, program.repeat_id)
+    }
+
+    async fn current_repeat(&self, program: &str) -> Result<i32> {
+        let tables = self.tables();
+
+        tables
+            .program_repeats(program)
+            .filter_map(|row| row.repeat_id)
+            .filter(|&repeat_id| !tables.is_complete(program, repeat_id))
+            .min_by_key(|&repeat_id| {
+                let active = tables
+                    .latest_execution(program, repeat_id)
+                    .is_some_and(|exec| {
+                        matches!(
+                            exec.state,
+                            ProgramState::Initiated | ProgramState::Processing
+                        )
+                    });
+
+                (!active, repeat_id)
+            })
+            .ok_or_else(|| {
+                Error::NotFound(format!("Program {} has no repeats left to run", program))
+            })
+    }
+
+    async fn get_history(&self, program: &str) -> Result<Vec<ProgramExecution>> {
+        Ok(self
+            .tables()
+            .executions
+            .iter()
+            .filter(|exec| exec.program_name == program)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_latest_execution(
+        &self,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Option<ProgramExecution>> {
+        Ok(self.tables().latest_execution(program, repeat_id).cloned())
+    }
+
+    async fn other_repeats_processing(&self, program: &str, repeat_id: i32) -> Result<bool> {
+        let repeats = self.tables().processing_repeats(program);
+
+        Ok(repeats.into_iter().any(|repeat| repeat != repeat_id))
+    }
+
+    async fn transition(
+        &self,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<ProgramExecution> {
+        self.tables().transaction(|tables| {
+            let execution = tables.transition(program, repeat_id, state, batch, operator)?;
+
+            match (state, batch) {
+                (ProgramState::Processing, Some(id)) => {
+                    let batch = tables.batch(id)?;
+                    tables.assign_batch(&batch, program, repeat_id)?;
+                    tables.push_batch_dimensions(&batch)?;
+                }
+                (ProgramState::Initiated | ProgramState::Cancelled, _) => {
+                    tables.release_batch(program, repeat_id);
+                }
+                _ => (),
+            }
+
+            Ok(execution)
+        })
+    }
+
+    async fn get_batches(&self) -> Result<Vec<Batch>> {
+        Ok(self.tables().active_batches())
+    }
+
+    async fn get_batch(&self, id: &str) -> Result<Batch> {
+        self.tables().batch(id)
+    }
+
+    async fn create_batch(&self, batch: &Batch) -> Result<()> {
+        let mut tables = self.tables();
+        tables.validate_batch(batch)?;
+
+        match tables.batches.iter_mut().find(|(b, _)| b.id == batch.id) {
+            Some((stored, retired)) if *retired => {
+                *stored = batch.clone();
+                *retired = false;
+            }
+            Some(_) => {
+                return Err(Error::Conflict(format!(
+                    "Batch {} already exists",
+                    batch.id
+                )))
+            }
+            None => tables.batches.push((batch.clone(), false)),
+        }
+
+        Ok(())
+    }
+
+    async fn update_batch(&self, id: &str, update: BatchUpdate) -> Result<Batch> {
+        self.tables().update_batch(id, update)
+    }
+
+    async fn retire_batch(&self, id: &str) -> Result<()> {
+        match self
+            .tables()
+            .batches
+            .iter_mut()
+            .find(|(batch, retired)| batch.id == id && !retired)
+        {
+            Some((_, retired)) => {
+                *retired = true;
+                Ok(())
+            }
+            None => Err(Error::NotFound(format!("Batch {} not found", id))),
+        }
+    }
+
+    async fn load_batch_dimensions(
+        &self,
+        rows: Vec<BatchDimensionsRow>,
+    ) -> Result<BatchDimensionsReport> {
+        let mut tables = self.tables();
+        let mut report = BatchDimensionsReport::default();
+
+        for row in rows {
+            let result = row.to_update().and_then(|update| {
+                row.check_sheet(&tables.batch(&row.batch)?)?;
+                tables.update_batch(&row.batch, update)
+            });
+            report.add(row.batch, result)?;
+        }
+
+        Ok(report)
+    }
+
+    async fn get_batch_candidates(
+        &self,
+        batches: Vec<Batch>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<BatchCandidate>> {
+        self.tables().batch_candidates(batches, program, repeat_id)
+    }
+
+    async fn check_batch_assignable(
+        &self,
+        id: &str,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Batch> {
+        let tables = self.tables();
+        let candidate = tables
+            .batch_candidates(tables.active_batches(), program, repeat_id)?
+            .into_iter()
+            .find(|candidate| candidate.batch.id == id)
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "Batch {} cannot be used for program {} repeat {}",
+                    id, program, repeat_id
+                ))
+            })?;
+        tables.check_consumer(id, program, repeat_id)?;
+
+        Ok(candidate.batch)
+    }
+
+    async fn get_feedback(&self) -> Result<FeedbackReport<FeedbackEntry<Nest>>> {
+        feedback_from_rows(self.tables().snapshot.feedback_rows()?)
+    }
+
+    async fn delete_feedback(&self, archive_packet_id: i32) -> Result<()> {
+        let mut tables = self.tables();
+        let count = tables.snapshot.program_feedback.len() + tables.snapshot.part_feedback.len();
+
+        tables
+            .snapshot
+            .program_feedback
+            .retain(|row| row.archive_packet_id != Some(archive_packet_id));
+        tables
+            .snapshot
+            .part_feedback
+            .retain(|row| row.archive_packet_id != Some(archive_packet_id));
+
+        match count == tables.snapshot.program_feedback.len() + tables.snapshot.part_feedback.len()
+        {
+            true => Err(Error::NotFound(format!(
+                "No feedback with ArchivePacketID {}",
+                archive_packet_id
+            ))),
+            false => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Storage used by the server routes
+//!
+//! [`SqlStore`] runs against the Sigmanest database. [`MemoryStore`] keeps
+//! the same data in memory, seeded from a `scripts/snapshot.py` dump, so the
+//! routes can be run without a database.
+
+use async_trait::async_trait;
+
+use super::api::{
+    FeedbackEntry, FeedbackReport, Machine, MachineUpdate, Nest, Program, ProgramExecution,
+    ProgramState, ProgramSummary,
+};
+use crate::{
+    batch::{Batch, BatchCandidate, BatchDimensionsReport, BatchDimensionsRow, BatchUpdate},
+    Result,
+};
+
+mod memory;
+mod snapshot;
+mod sql;
+
+pub use memory::{MemoryStore, SimTransaction, REMNANT_DXF_TEMPLATE, SIM_TRANS_DISTRICTS};
+pub use snapshot::Snapshot;
+pub use sql::SqlStore;
+
+#[async_trait]
+pub trait SigmanestStore: std::fmt::Debug + Send + Sync {
+    /// checks if the store can be reached
+    fn is_available(&self) -> bool;
+
+    /// get all machines that have programs or are in the registry
+    async fn get_machines(&self) -> Result<Vec<Machine>>;
+
+    async fn get_machine(&self, name: &str) -> Result<Machine>;
+
+    /// adds a machine to the registry, or updates its registry entry
+    async fn update_machine(&self, name: &str, update: MachineUpdate) -> Result<Machine>;
+
+    /// get the NC queue folder of a machine, if it has one in the registry
+    async fn get_nc_queue(&self, machine: &str) -> Result<Option<String>>;
+
+    /// get the machines a program can be moved to
+    async fn get_alternate_machines(&self, program: &str) -> Result<Vec<Machine>>;
+
+    /// moves all repeats of a program to another machine
+    async fn reassign_program(&self, program: &str, machine: &str) -> Result<Machine>;
+
+    /// get programs on a machine that have repeats left to run
+    async fn get_programs(
+        &self,
+        machine: &str,
+        hide_unavailable: bool,
+    ) -> Result<Vec<ProgramSummary>>;
+
+    async fn get_program(&self, program: &str, repeat_id: i32) -> Result<Program>;
+
+    /// get a program repeat with its parts, sheet and remnants
+    async fn get_nest(&self, program: &str, repeat_id: i32) -> Result<Nest>;
+
+    async fn get_nest_by_archive_packet_id(&self, archive_packet_id: i32) -> Result<Nest>;
+
+    /// get the repeat of a program that is being worked on, or the next
+    /// repeat that can be started
+    async fn current_repeat(&self, program: &str) -> Result<i32>;
+
+    /// get all recorded state changes of a program, oldest first
+    async fn get_history(&self, program: &str) -> Result<Vec<ProgramExecution>>;
+
+    /// get the current state of a program repeat, if it has one
+    async fn get_latest_execution(
+        &self,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Option<ProgramExecution>>;
+
+    /// checks if any other repeat of the program is processing
+    async fn other_repeats_processing(&self, program: &str, repeat_id: i32) -> Result<bool>;
+
+    /// records a state change of a program repeat
+    ///
+    /// Completing a repeat pushes its SimTrans `SN70` transaction. Processing
+    /// assigns `batch` to the repeat and pushes its actual dimensions, and
+    /// going back to initiated or cancelling releases it. All of it is
+    /// applied together, or not at all.
+    async fn transition(
+        &self,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<ProgramExecution>;
+
+    /// get all batches that are not retired
+    async fn get_batches(&self) -> Result<Vec<Batch>>;
+
+    async fn get_batch(&self, id: &str) -> Result<Batch>;
+
+    /// adds a batch, or brings back a retired batch with the same id
+    async fn create_batch(&self, batch: &Batch) -> Result<()>;
+
+    async fn update_batch(&self, id: &str, update: BatchUpdate) -> Result<Batch>;
+
+    async fn retire_batch(&self, id: &str) -> Result<()>;
+
+    /// sets the actual dimensions of non-standard batches
+    async fn load_batch_dimensions(
+        &self,
+        rows: Vec<BatchDimensionsRow>,
+    ) -> Result<BatchDimensionsReport>;
+
+    /// get the batches that can be used for a program repeat, best match first
+    async fn get_batch_candidates(
+        &self,
+        batches: Vec<Batch>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<BatchCandidate>>;
+
+    /// checks the batch can be used to process a program repeat
+    async fn check_batch_assignable(
+        &self,
+        id: &str,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Batch>;
+
+    /// get program feedback, with the parts and remnants of posted nests
+    ///
+    /// Nests with rows that cannot be read are left out and reported as skipped.
+    async fn get_feedback(&self) -> Result<FeedbackReport<FeedbackEntry<Nest>>>;
+
+    /// deletes the program and part feedback of an `ArchivePacketID`
+    async fn delete_feedback(&self, archive_packet_id: i32) -> Result<()>;
+}
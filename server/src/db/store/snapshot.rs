@@ -0,0 +1,729 @@
+//! Database snapshots dumped by `scripts/snapshot.py`
+//!
+//! A snapshot is a JSON object of table name to rows, with each row an object
+//! of column name to value. Values JSON has no type for (i.e. dates and
+//! decimals) are dumped as text. Only the tables and columns the server reads
+//! are loaded, so `STRemArc`, `STShtArc` and `STWOArc` are ignored.
+//!
+//! The rows the export and nest queries read are emulated here, to be
+//! assembled by the same functions as database rows
+//! ([`feedback_from_rows`](crate::db::exports::feedback_from_rows) and [`Nest::from_rows`]).
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::{
+    db::{
+        api::{
+            FeedbackEntry, FeedbackReport, Nest, Part, Program, Remnant, Sheet, SheetSize,
+            TransactionType,
+        },
+        exports::NestFeedbackRows,
+    },
+    Error, Result,
+};
+use sndb::model::NestRows;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Snapshot {
+    #[serde(rename = "STPrgArc", default)]
+    pub(crate) program_feedback: Vec<ProgramFeedbackRow>,
+    #[serde(rename = "STPIPArc", default)]
+    pub(crate) part_feedback: Vec<PartFeedbackRow>,
+    #[serde(rename = "STPrtArc", default)]
+    pub(crate) complete_part_feedback: Vec<CompletePartFeedbackRow>,
+    #[serde(rename = "Program", default)]
+    pub(crate) programs: Vec<ProgramRow>,
+    #[serde(rename = "PIP", default)]
+    pub(crate) program_parts: Vec<ProgramPartRow>,
+    #[serde(rename = "Stock", default)]
+    pub(crate) stock: Vec<StockRow>,
+    #[serde(rename = "Part", default)]
+    pub(crate) parts: Vec<PartRow>,
+    #[serde(rename = "Remnant", default)]
+    pub(crate) remnants: Vec<RemnantRow>,
+}
+
+impl Snapshot {
+    /// loads a snapshot file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!("failed to read snapshot {}: {}", path.display(), e))
+        })?;
+
+        Self::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::Config(format!("invalid snapshot: {}", e)))
+    }
+
+    /// get in process and updated programs from feedback
+    pub fn program_feedback(&self) -> Result<FeedbackReport<FeedbackEntry<Program>>> {
+        FeedbackReport::by_nest(
+            self.program_feedback
+                .iter()
+                .map(|row| (row.archive_packet_id, row.entry(|_| row.program()))),
+        )
+    }
+
+    /// get in process parts from feedback
+    pub fn part_feedback(&self) -> Result<FeedbackReport<FeedbackEntry<Part>>> {
+        let entries = self.part_feedback.iter().filter_map(|row| {
+            let part = self.part(&row.part_name, &row.wo_number)?;
+
+            Some((row.archive_packet_id, row.entry(|_| row.part(part))))
+        });
+
+        FeedbackReport::by_nest(entries)
+    }
+
+    /// get updated parts from feedback
+    pub fn complete_part_feedback(&self) -> Result<FeedbackReport<Part>> {
+        FeedbackReport::by_nest(
+            self.complete_part_feedback
+                .iter()
+                .map(|row| (row.archive_packet_id, row.part())),
+        )
+    }
+
+    pub fn programs(&self) -> Result<Vec<Program>> {
+        self.programs.iter().map(ProgramRow::program).collect()
+    }
+
+    pub fn sheets(&self) -> Vec<Sheet> {
+        self.stock.iter().map(StockRow::sheet).collect()
+    }
+
+    /// get remnants to be created by programs, with their program and repeat
+    pub fn future_remnants(&self) -> Result<Vec<(String, i32, Remnant)>> {
+        self.remnants
+            .iter()
+            .map(|row| {
+                let (program, repeat_id) = row.program()?;
+
+                Ok((program, repeat_id, row.remnant()?))
+            })
+            .collect()
+    }
+
+    /// reads program feedback and the parts and remnants of its posted nests,
+    /// like `export_feedback` does
+    pub fn feedback_rows(&self) -> Result<FeedbackReport<(FeedbackEntry<Nest>, NestFeedbackRows)>> {
+        // program feedback is joined to the program's sheet
+        let entries = self
+            .program_feedback
+            .iter()
+            .flat_map(|row| {
+                self.stock
+                    .iter()
+                    .filter(|stock| joins(&stock.sheet_name, &row.sheet_name))
+                    .map(move |stock| (row, stock))
+            })
+            .map(|(row, stock)| {
+                let entry = row.entry(|archive_packet_id| {
+                    Ok(Nest {
+                        archive_packet_id,
+                        program: row.program()?,
+                        parts: Vec::new(),
+                        sheet: stock.sheet(),
+                        remnants: Vec::new(),
+                    })
+                });
+
+                (row.archive_packet_id, entry)
+            });
+        let programs = FeedbackReport::by_nest(entries)?;
+
+        let feedback = programs
+            .feedback
+            .into_iter()
+            .map(|program| {
+                let rows = match program.state {
+                    TransactionType::Created(ref nest) => NestFeedbackRows {
+                        parts: self.posted_parts(program.archive_packet_id),
+                        remnants: self
+                            .program_remnants(&nest.program.program_name, nest.program.repeat_id),
+                    },
+                    _ => NestFeedbackRows::default(),
+                };
+
+                (program, rows)
+            })
+            .collect();
+
+        Ok(FeedbackReport {
+            feedback,
+            skipped: programs.skipped,
+        })
+    }
+
+    /// reads the rows of a program repeat, like `Nest::get` does
+    pub fn nest_rows(&self, program: &str, repeat_id: i32) -> NestRows {
+        let rows: Vec<&ProgramRow> = self
+            .programs
+            .iter()
+            .filter(|row| row.is(program, repeat_id))
+            .collect();
+
+        // parts are joined to `Part` by name only, with duplicate rows removed
+        let mut part_rows = Vec::new();
+        for pip in self
+            .program_parts
+            .iter()
+            .filter(|pip| pip.is(program, repeat_id))
+        {
+            for part in self
+                .parts
+                .iter()
+                .filter(|part| joins(&part.part_name, &pip.part_name))
+            {
+                let columns = (
+                    &pip.wo_number,
+                    &pip.part_name,
+                    pip.qty_in_process,
+                    &part.data1,
+                    &part.data2,
+                    part.true_area,
+                    part.nested_area,
+                );
+                if !part_rows.iter().any(|(seen, _, _)| *seen == columns) {
+                    part_rows.push((columns, pip, part));
+                }
+            }
+        }
+
+        let mut sheets: Vec<&StockRow> = Vec::new();
+        for row in &rows {
+            for stock in self
+                .stock
+                .iter()
+                .filter(|stock| joins(&stock.sheet_name, &row.sheet_name))
+            {
+                let is_duplicate = sheets.iter().any(|seen| {
+                    seen.sheet_name == stock.sheet_name && seen.prime_code == stock.prime_code
+                });
+                if !is_duplicate {
+                    sheets.push(stock);
+                }
+            }
+        }
+
+        NestRows {
+            program: rows.first().map(|row| {
+                let program = row.program()?;
+                let archive_packet_id = required(
+                    "Program",
+                    &row.row_id(),
+                    "ArchivePacketID",
+                    &row.archive_packet_id,
+                )?;
+
+                Ok((archive_packet_id, program))
+            }),
+            parts: part_rows
+                .into_iter()
+                .map(|(_, pip, part)| pip.part(part))
+                .collect(),
+            sheets: sheets.into_iter().map(|stock| Ok(stock.sheet())).collect(),
+            remnants: self
+                .remnants
+                .iter()
+                .filter(|row| {
+                    row.program_name.as_deref() == Some(program) && row.repeat_id == Some(repeat_id)
+                })
+                .map(RemnantRow::remnant)
+                .collect(),
+        }
+    }
+
+    /// get the in process parts posted with a program, like `Part::get_ip_feedback_by_program`
+    fn posted_parts(&self, archive_packet_id: i32) -> Result<Vec<Part>> {
+        self.part_feedback
+            .iter()
+            .filter(|row| {
+                row.archive_packet_id == Some(archive_packet_id)
+                    && row.trans_type.as_deref() == Some("SN100")
+            })
+            .flat_map(|row| {
+                self.parts
+                    .iter()
+                    .filter(|part| {
+                        joins(&part.part_name, &row.part_name)
+                            && joins(&part.wo_number, &row.wo_number)
+                    })
+                    .map(|part| row.part(part))
+            })
+            .collect()
+    }
+
+    /// get the remnants a program repeat creates, like `Remnant::get_future_remnants_by_program`
+    fn program_remnants(&self, program: &str, repeat_id: i32) -> Result<Vec<Remnant>> {
+        self.remnants
+            .iter()
+            .filter(|row| {
+                row.program_name.as_deref() == Some(program) && row.repeat_id == Some(repeat_id)
+            })
+            .map(RemnantRow::remnant)
+            .collect()
+    }
+
+    /// get the `Part` row of in process feedback
+    pub(crate) fn part(
+        &self,
+        part_name: &Option<String>,
+        wo_number: &Option<String>,
+    ) -> Option<&PartRow> {
+        self.parts
+            .iter()
+            .find(|part| &part.part_name == part_name && &part.wo_number == wo_number)
+    }
+}
+
+/// A row of `STPrgArc`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProgramFeedbackRow {
+    #[serde(rename = "AutoID", default, deserialize_with = "number")]
+    pub auto_id: Option<i32>,
+    #[serde(rename = "ArchivePacketID", default, deserialize_with = "number")]
+    pub archive_packet_id: Option<i32>,
+    #[serde(rename = "TransType", default)]
+    pub trans_type: Option<String>,
+    #[serde(rename = "ProgramName", default)]
+    pub program_name: Option<String>,
+    #[serde(
+        rename = "RepeatID",
+        alias = "RepeatId",
+        default,
+        deserialize_with = "number"
+    )]
+    pub repeat_id: Option<i32>,
+    #[serde(rename = "MachineName", default)]
+    pub machine_name: Option<String>,
+    #[serde(rename = "CuttingTime", default, deserialize_with = "number")]
+    pub cutting_time: Option<f64>,
+    #[serde(rename = "SheetName", default)]
+    pub sheet_name: Option<String>,
+}
+
+/// A row of `STPIPArc`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PartFeedbackRow {
+    #[serde(rename = "AutoID", default, deserialize_with = "number")]
+    pub auto_id: Option<i32>,
+    #[serde(rename = "ArchivePacketID", default, deserialize_with = "number")]
+    pub archive_packet_id: Option<i32>,
+    #[serde(rename = "TransType", default)]
+    pub trans_type: Option<String>,
+    #[serde(rename = "PartName", default)]
+    pub part_name: Option<String>,
+    #[serde(rename = "WONumber", default)]
+    pub wo_number: Option<String>,
+    #[serde(rename = "QtyInProcess", default, deserialize_with = "number")]
+    pub qty_in_process: Option<i32>,
+}
+
+/// A row of `STPrtArc`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct CompletePartFeedbackRow {
+    #[serde(rename = "AutoID", default, deserialize_with = "number")]
+    pub auto_id: Option<i32>,
+    #[serde(rename = "ArchivePacketID", default, deserialize_with = "number")]
+    pub archive_packet_id: Option<i32>,
+    #[serde(rename = "PartName", default)]
+    pub part_name: Option<String>,
+    #[serde(rename = "QtyProgram", default, deserialize_with = "number")]
+    pub qty_program: Option<i32>,
+    /// job
+    #[serde(rename = "Data1", default)]
+    pub data1: Option<String>,
+    /// shipment
+    #[serde(rename = "Data2", default)]
+    pub data2: Option<String>,
+    #[serde(rename = "TrueArea", default, deserialize_with = "number")]
+    pub true_area: Option<f64>,
+    #[serde(rename = "NestedArea", default, deserialize_with = "number")]
+    pub nested_area: Option<f64>,
+}
+
+/// A row of `Program`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProgramRow {
+    #[serde(rename = "ProgramName", default)]
+    pub program_name: Option<String>,
+    #[serde(
+        rename = "RepeatID",
+        alias = "RepeatId",
+        default,
+        deserialize_with = "number"
+    )]
+    pub repeat_id: Option<i32>,
+    #[serde(rename = "ArchivePacketID", default, deserialize_with = "number")]
+    pub archive_packet_id: Option<i32>,
+    #[serde(rename = "MachineName", default)]
+    pub machine_name: Option<String>,
+    #[serde(rename = "CuttingTime", default, deserialize_with = "number")]
+    pub cutting_time: Option<f64>,
+    #[serde(rename = "SheetName", default)]
+    pub sheet_name: Option<String>,
+}
+
+/// A row of `PIP`, the parts nested in a program
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProgramPartRow {
+    #[serde(rename = "ProgramName", default)]
+    pub program_name: Option<String>,
+    #[serde(
+        rename = "RepeatID",
+        alias = "RepeatId",
+        default,
+        deserialize_with = "number"
+    )]
+    pub repeat_id: Option<i32>,
+    #[serde(rename = "PartName", default)]
+    pub part_name: Option<String>,
+    #[serde(rename = "WONumber", default)]
+    pub wo_number: Option<String>,
+    #[serde(rename = "QtyInProcess", default, deserialize_with = "number")]
+    pub qty_in_process: Option<i32>,
+}
+
+/// A row of `Stock`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StockRow {
+    #[serde(rename = "SheetName", default)]
+    pub sheet_name: Option<String>,
+    #[serde(rename = "PrimeCode", default)]
+    pub prime_code: Option<String>,
+    #[serde(rename = "Thickness", default, deserialize_with = "number")]
+    pub thickness: Option<f64>,
+    #[serde(rename = "Width", default, deserialize_with = "number")]
+    pub width: Option<f64>,
+    #[serde(rename = "Length", default, deserialize_with = "number")]
+    pub length: Option<f64>,
+    /// remnant geometry, only set for remnants
+    #[serde(rename = "FileName", default)]
+    pub file_name: Option<String>,
+}
+
+/// A row of `Part`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PartRow {
+    #[serde(rename = "PartName", default)]
+    pub part_name: Option<String>,
+    #[serde(rename = "WONumber", default)]
+    pub wo_number: Option<String>,
+    /// job
+    #[serde(rename = "Data1", default)]
+    pub data1: Option<String>,
+    /// shipment
+    #[serde(rename = "Data2", default)]
+    pub data2: Option<String>,
+    #[serde(rename = "TrueArea", default, deserialize_with = "number")]
+    pub true_area: Option<f64>,
+    #[serde(rename = "NestedArea", default, deserialize_with = "number")]
+    pub nested_area: Option<f64>,
+}
+
+/// A row of `Remnant`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RemnantRow {
+    #[serde(rename = "RemnantName", default)]
+    pub remnant_name: Option<String>,
+    #[serde(rename = "ProgramName", default)]
+    pub program_name: Option<String>,
+    #[serde(
+        rename = "RepeatID",
+        alias = "RepeatId",
+        default,
+        deserialize_with = "number"
+    )]
+    pub repeat_id: Option<i32>,
+    #[serde(rename = "Length", default, deserialize_with = "number")]
+    pub length: Option<f64>,
+    #[serde(rename = "Width", default, deserialize_with = "number")]
+    pub width: Option<f64>,
+    #[serde(rename = "Area", default, deserialize_with = "number")]
+    pub area: Option<f64>,
+}
+
+impl ProgramFeedbackRow {
+    pub fn program(&self) -> Result<Program> {
+        let row = self.row_id();
+
+        Ok(Program {
+            program_name: required("STPrgArc", &row, "ProgramName", &self.program_name)?,
+            repeat_id: required("STPrgArc", &row, "RepeatID", &self.repeat_id)?,
+            machine_name: required("STPrgArc", &row, "MachineName", &self.machine_name)?,
+            cutting_time: required("STPrgArc", &row, "CuttingTime", &self.cutting_time)?,
+        })
+    }
+
+    /// the feedback entry, with `created` building the data of posted programs
+    pub fn entry<T>(&self, created: impl FnOnce(i32) -> Result<T>) -> Result<FeedbackEntry<T>> {
+        feedback_entry(
+            "STPrgArc",
+            &self.row_id(),
+            self.archive_packet_id,
+            self.trans_type.as_deref(),
+            created,
+        )
+    }
+
+    fn row_id(&self) -> String {
+        row_id(&[
+            ("AutoID", self.auto_id.map(|id| id.to_string())),
+            (
+                "ArchivePacketID",
+                self.archive_packet_id.map(|id| id.to_string()),
+            ),
+        ])
+    }
+}
+
+impl PartFeedbackRow {
+    /// the in process part, with the details from its `Part` row
+    pub fn part(&self, part: &PartRow) -> Result<Part> {
+        let row = self.row_id();
+
+        Ok(Part {
+            part_name: self.part_name.clone().unwrap_or_default(),
+            part_qty: required("STPIPArc", &row, "QtyInProcess", &self.qty_in_process)?,
+            job: part.data1.clone().unwrap_or_default(),
+            shipment: shipment("Part", &row, &part.data2)?,
+            true_area: required("Part", &row, "TrueArea", &part.true_area)?,
+            nested_area: required("Part", &row, "NestedArea", &part.nested_area)?,
+        })
+    }
+
+    /// the feedback entry, with `created` building the data of posted parts
+    pub fn entry<T>(&self, created: impl FnOnce(i32) -> Result<T>) -> Result<FeedbackEntry<T>> {
+        feedback_entry(
+            "STPIPArc",
+            &self.row_id(),
+            self.archive_packet_id,
+            self.trans_type.as_deref(),
+            created,
+        )
+    }
+
+    fn row_id(&self) -> String {
+        row_id(&[
+            ("AutoID", self.auto_id.map(|id| id.to_string())),
+            ("PartName", self.part_name.clone()),
+        ])
+    }
+}
+
+impl CompletePartFeedbackRow {
+    pub fn part(&self) -> Result<Part> {
+        let row = row_id(&[
+            ("AutoID", self.auto_id.map(|id| id.to_string())),
+            ("PartName", self.part_name.clone()),
+        ]);
+
+        Ok(Part {
+            part_name: self.part_name.clone().unwrap_or_default(),
+            part_qty: required("STPrtArc", &row, "QtyProgram", &self.qty_program)?,
+            job: self.data1.clone().unwrap_or_default(),
+            shipment: shipment("STPrtArc", &row, &self.data2)?,
+            true_area: required("STPrtArc", &row, "TrueArea", &self.true_area)?,
+            nested_area: required("STPrtArc", &row, "NestedArea", &self.nested_area)?,
+        })
+    }
+}
+
+impl ProgramRow {
+    pub fn program(&self) -> Result<Program> {
+        let row = self.row_id();
+
+        Ok(Program {
+            program_name: required("Program", &row, "ProgramName", &self.program_name)?,
+            repeat_id: required("Program", &row, "RepeatID", &self.repeat_id)?,
+            machine_name: required("Program", &row, "MachineName", &self.machine_name)?,
+            cutting_time: required("Program", &row, "CuttingTime", &self.cutting_time)?,
+        })
+    }
+
+    pub fn is(&self, program: &str, repeat_id: i32) -> bool {
+        self.program_name.as_deref() == Some(program) && self.repeat_id == Some(repeat_id)
+    }
+
+    fn row_id(&self) -> String {
+        row_id(&[
+            (
+                "ArchivePacketID",
+                self.archive_packet_id.map(|id| id.to_string()),
+            ),
+            ("ProgramName", self.program_name.clone()),
+        ])
+    }
+}
+
+impl ProgramPartRow {
+    /// the nested part, with the details from its `Part` row
+    pub fn part(&self, part: &PartRow) -> Result<Part> {
+        let row = row_id(&[("PartName", self.part_name.clone())]);
+
+        Ok(Part {
+            part_name: self.part_name.clone().unwrap_or_default(),
+            part_qty: required("PIP", &row, "QtyInProcess", &self.qty_in_process)?,
+            job: part.data1.clone().unwrap_or_default(),
+            shipment: shipment("Part", &row, &part.data2)?,
+            true_area: required("Part", &row, "TrueArea", &part.true_area)?,
+            nested_area: required("Part", &row, "NestedArea", &part.nested_area)?,
+        })
+    }
+
+    pub fn is(&self, program: &str, repeat_id: i32) -> bool {
+        self.program_name.as_deref() == Some(program) && self.repeat_id == Some(repeat_id)
+    }
+}
+
+impl StockRow {
+    pub fn sheet(&self) -> Sheet {
+        Sheet {
+            sheet_name: self.sheet_name.clone().unwrap_or_default(),
+            material_master: self.prime_code.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn size(&self) -> Result<SheetSize> {
+        let row = row_id(&[("SheetName", self.sheet_name.clone())]);
+
+        Ok(SheetSize {
+            sheet_name: required("Stock", &row, "SheetName", &self.sheet_name)?,
+            thickness: required("Stock", &row, "Thickness", &self.thickness)?,
+            width: required("Stock", &row, "Width", &self.width)?,
+            length: required("Stock", &row, "Length", &self.length)?,
+        })
+    }
+}
+
+impl RemnantRow {
+    pub fn remnant(&self) -> Result<Remnant> {
+        let row = self.row_id();
+
+        Ok(Remnant {
+            remnant_name: required("Remnant", &row, "RemnantName", &self.remnant_name)?,
+            length: required("Remnant", &row, "Length", &self.length)?,
+            width: required("Remnant", &row, "Width", &self.width)?,
+            area: required("Remnant", &row, "Area", &self.area)?,
+        })
+    }
+
+    /// the program and repeat that create the remnant
+    pub fn program(&self) -> Result<(String, i32)> {
+        let row = self.row_id();
+
+        Ok((
+            required("Remnant", &row, "ProgramName", &self.program_name)?,
+            required("Remnant", &row, "RepeatID", &self.repeat_id)?,
+        ))
+    }
+
+    fn row_id(&self) -> String {
+        row_id(&[("RemnantName", self.remnant_name.clone())])
+    }
+}
+
+/// maps a feedback transaction, like `TransactionType` does for database rows
+fn feedback_entry<T>(
+    table: &'static str,
+    row: &str,
+    archive_packet_id: Option<i32>,
+    trans_type: Option<&str>,
+    created: impl FnOnce(i32) -> Result<T>,
+) -> Result<FeedbackEntry<T>> {
+    let archive_packet_id = required(table, row, "ArchivePacketID", &archive_packet_id)?;
+    let state = match trans_type {
+        Some("SN100") => TransactionType::Created(created(archive_packet_id)?),
+        Some("SN101") => TransactionType::Deleted,
+        Some("SN102") => TransactionType::Updated,
+        Some(tcode) => {
+            return Err(invalid(
+                table,
+                row,
+                "TransType",
+                &format!("`{}` is not a feedback transaction", tcode),
+            ))
+        }
+        None => return Err(invalid(table, row, "TransType", "is NULL")),
+    };
+
+    Ok(FeedbackEntry {
+        archive_packet_id,
+        batch: None,
+        state,
+    })
+}
+
+/// checks if two columns match in an inner join, where NULL matches nothing
+fn joins(left: &Option<String>, right: &Option<String>) -> bool {
+    left.is_some() && left == right
+}
+
+/// describes a row by the first identifying column it has, like `SqlRow`
+fn row_id(columns: &[(&str, Option<String>)]) -> String {
+    columns
+        .iter()
+        .find_map(|(column, value)| value.as_ref().map(|id| format!("{}={}", column, id)))
+        .unwrap_or_else(|| String::from("unidentified row"))
+}
+
+fn required<T: Clone>(
+    table: &'static str,
+    row: &str,
+    column: &str,
+    value: &Option<T>,
+) -> Result<T> {
+    value
+        .clone()
+        .ok_or_else(|| invalid(table, row, column, "is NULL"))
+}
+
+/// reads the shipment number kept in `Data2`, where blank means none
+fn shipment(table: &'static str, row: &str, data2: &Option<String>) -> Result<i32> {
+    match data2.as_deref().map(str::trim) {
+        None | Some("") => Ok(0),
+        Some(shipment) => shipment
+            .parse()
+            .map_err(|_| invalid(table, row, "Data2", "is not a shipment number")),
+    }
+}
+
+fn invalid(table: &'static str, row: &str, column: &str, problem: &str) -> Error {
+    Error::RowMapping {
+        table,
+        row: String::from(row),
+        column: String::from(column),
+        problem: String::from(problem),
+    }
+}
+
+/// reads a number dumped as a JSON number or as text
+fn number<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value<T> {
+        Number(T),
+        Text(String),
+    }
+
+    match Option::<Value<T>>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Value::Number(value)) => Ok(Some(value)),
+        Some(Value::Text(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| D::Error::custom(format!("`{}` is not a number: {}", text, e))),
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::SigmanestStore;
+use crate::{
+    batch::{Batch, BatchCandidate, BatchDimensionsReport, BatchDimensionsRow, BatchUpdate},
+    db::{
+        api::{
+            FeedbackEntry, FeedbackReport, Machine, MachineUpdate, Nest, Program, ProgramExecution,
+            ProgramState, ProgramSummary,
+        },
+        exports, DbHealth, DbPool, SqlConn, Transaction,
+    },
+    Result,
+};
+
+/// Store backed by the Sigmanest database
+///
+/// Each call checks out its own connection from the pool.
+#[derive(Debug)]
+pub struct SqlStore {
+    db: DbPool,
+    health: Arc<DbHealth>,
+
+    /// Name of SAP system (PRD, QAS, etc.) SimTrans transactions are pushed for.
+    /// Matches a `SapSystem` row in `dbo.SapInterfaceConfig`.
+    sap_system: String,
+}
+
+impl SqlStore {
+    /// wraps a pool, monitoring its connection
+    pub fn new(db: DbPool, sap_system: String) -> Self {
+        let health = Arc::new(DbHealth::default());
+        health.monitor(db.clone());
+
+        Self {
+            db,
+            health,
+            sap_system,
+        }
+    }
+
+    async fn conn(&self) -> Result<SqlConn<'_>> {
+        Ok(self.db.get().await?)
+    }
+}
+
+#[async_trait]
+impl SigmanestStore for SqlStore {
+    fn is_available(&self) -> bool {
+        self.health.is_available()
+    }
+
+    async fn get_machines(&self) -> Result<Vec<Machine>> {
+        Machine::get_all(&mut self.conn().await?).await
+    }
+
+    async fn get_machine(&self, name: &str) -> Result<Machine> {
+        Machine::get(&mut self.conn().await?, name).await
+    }
+
+    async fn update_machine(&self, name: &str, update: MachineUpdate) -> Result<Machine> {
+        Machine::update(&mut self.conn().await?, name, update).await
+    }
+
+    async fn get_nc_queue(&self, machine: &str) -> Result<Option<String>> {
+        Machine::get_nc_queue(&mut self.conn().await?, machine).await
+    }
+
+    async fn get_alternate_machines(&self, program: &str) -> Result<Vec<Machine>> {
+        Machine::get_alternates(&mut self.conn().await?, program).await
+    }
+
+    async fn reassign_program(&self, program: &str, machine: &str) -> Result<Machine> {
+        Machine::reassign(&mut self.conn().await?, program, machine).await
+    }
+
+    async fn get_programs(
+        &self,
+        machine: &str,
+        hide_unavailable: bool,
+    ) -> Result<Vec<ProgramSummary>> {
+        ProgramSummary::get_by_machine(&mut self.conn().await?, machine, hide_unavailable).await
+    }
+
+    async fn get_program(&self, program: &str, repeat_id: i32) -> Result<Program> {
+        Program::get(&mut self.conn().await?, program, repeat_id).await
+    }
+
+    async fn get_nest(&self, program: &str, repeat_id: i32) -> Result<Nest> {
+        Nest::get(&mut self.conn().await?, program, repeat_id).await
+    }
+
+    async fn get_nest_by_archive_packet_id(&self, archive_packet_id: i32) -> Result<Nest> {
+        Nest::get_by_archive_packet_id(&mut self.conn().await?, archive_packet_id).await
+    }
+
+    async fn current_repeat(&self, program: &str) -> Result<i32> {
+        ProgramExecution::current_repeat(&mut self.conn().await?, program).await
+    }
+
+    async fn get_history(&self, program: &str) -> Result<Vec<ProgramExecution>> {
+        ProgramExecution::get_history(&mut self.conn().await?, program).await
+    }
+
+    async fn get_latest_execution(
+        &self,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Option<ProgramExecution>> {
+        ProgramExecution::get_latest(&mut self.conn().await?, program, repeat_id).await
+    }
+
+    async fn other_repeats_processing(&self, program: &str, repeat_id: i32) -> Result<bool> {
+        ProgramExecution::other_repeats_processing(&mut self.conn().await?, program, repeat_id)
+            .await
+    }
+
+    async fn transition(
+        &self,
+        program: &str,
+        repeat_id: i32,
+        state: ProgramState,
+        batch: Option<&str>,
+        operator: Option<&str>,
+    ) -> Result<ProgramExecution> {
+        let mut conn = self.conn().await?;
+        let mut tx = Transaction::begin(&mut conn).await?;
+        let result = async {
+            let execution = ProgramExecution::transition(
+                &mut tx,
+                &self.sap_system,
+                program,
+                repeat_id,
+                state,
+                batch,
+                operator,
+            )
+            .await?;
+
+            match (state, batch) {
+                (ProgramState::Processing, Some(id)) => {
+                    let batch = Batch::get(&mut tx, id).await?;
+                    batch.assign(&mut tx, program, repeat_id).await?;
+
+                    // non-standard batches update their sheet to the actual dimensions
+                    batch.push_dimensions(&mut tx, &self.sap_system).await?;
+                }
+                (ProgramState::Initiated | ProgramState::Cancelled, _) => {
+                    Batch::release(&mut tx, program, repeat_id).await?;
+                }
+                _ => (),
+            }
+
+            Ok(execution)
+        }
+        .await;
+
+        tx.end(result).await
+    }
+
+    async fn get_batches(&self) -> Result<Vec<Batch>> {
+        Batch::get_batches(&mut self.conn().await?).await
+    }
+
+    async fn get_batch(&self, id: &str) -> Result<Batch> {
+        Batch::get(&mut self.conn().await?, id).await
+    }
+
+    async fn create_batch(&self, batch: &Batch) -> Result<()> {
+        batch.create(&mut self.conn().await?).await
+    }
+
+    async fn update_batch(&self, id: &str, update: BatchUpdate) -> Result<Batch> {
+        Batch::update(&mut self.conn().await?, id, update).await
+    }
+
+    async fn retire_batch(&self, id: &str) -> Result<()> {
+        Batch::retire(&mut self.conn().await?, id).await
+    }
+
+    async fn load_batch_dimensions(
+        &self,
+        rows: Vec<BatchDimensionsRow>,
+    ) -> Result<BatchDimensionsReport> {
+        Batch::load_dimensions(&mut self.conn().await?, rows).await
+    }
+
+    async fn get_batch_candidates(
+        &self,
+        batches: Vec<Batch>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<BatchCandidate>> {
+        Batch::get_candidates(&mut self.conn().await?, batches, program, repeat_id).await
+    }
+
+    async fn check_batch_assignable(
+        &self,
+        id: &str,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Batch> {
+        Batch::check_assignable(&mut self.conn().await?, id, program, repeat_id).await
+    }
+
+    async fn get_feedback(&self) -> Result<FeedbackReport<FeedbackEntry<Nest>>> {
+        exports::export_feedback(self.db.clone()).await
+    }
+
+    async fn delete_feedback(&self, archive_packet_id: i32) -> Result<()> {
+        exports::delete_feedback(&mut self.conn().await?, archive_packet_id).await
+    }
+}
@@ -1,61 +1,47 @@
+use std::sync::Arc;
+
 pub mod batch;
 pub mod db;
+pub mod nc;
+pub mod routes;
 
-pub mod error {
-    use axum::{
-        http::StatusCode,
-        response::{IntoResponse, Response},
-    };
-
-    // Error handling: see
-    //  https://docs.rs/axum/latest/axum/error_handling/index.html
-    //  https://github.com/tokio-rs/axum/blob/main/examples/anyhow-error-response/src/main.rs
-    #[derive(Debug, thiserror::Error)]
-    pub enum Error {
-        #[error("Database error: see server logs.")]
-        SqlError(#[from] tiberius::error::Error),
-        #[error("Database pool error: see server logs.")]
-        SqlPoolError,
-        #[error("Failed to parse csv file")]
-        CsvError,
-        #[error("Requested resource not found")]
-        NotFound(String),
-    }
+pub use sndb::{config, error, extract, Error, Result};
 
-    // Tell axum how to convert `AppError` into a response.
-    impl IntoResponse for Error {
-        fn into_response(self) -> Response {
-            // let error = match self {
-            //     Self::SqlError(e) => e.to_string(),
-            //     Self::SqlPoolError => todo!(),
-            //     Self::NotFound(s) => s,
-            // };
+use batch::BatchCache;
+use config::{DbConfig, NcConfig};
+use db::store::{SigmanestStore, SqlStore};
+use nc::NcStager;
 
-            (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
-        }
-    }
-
-    // impl From<SqlError> for Error {
-    //     fn from(value: SqlError) -> Self {
-    //         log::error!("Casting tiberius error to app error: {:#?}", value);
-    //         Self::SqlError
-    //     }
-    // }
+#[derive(Debug)]
+pub struct AppState {
+    pub store: Arc<dyn SigmanestStore>,
+    pub batches: BatchCache,
+    pub nc: NcStager,
+}
 
-    impl<T: std::fmt::Debug> From<bb8::RunError<T>> for Error {
-        fn from(value: bb8::RunError<T>) -> Self {
-            log::error!("Casting bb8 error to app error: {:#?}", value);
-            Self::SqlPoolError
-        }
+impl AppState {
+    /// connects to the Sigmanest database from the config files
+    pub fn new() -> Result<Self> {
+        let config = DbConfig::load()?;
+        let sap_system = std::env::var("SAP_SYSTEM")
+            .ok()
+            .or_else(|| config.profile.clone())
+            .unwrap_or_else(|| String::from("QAS"));
+        let db = db::build_db_pool(&config)?;
+
+        Ok(Self {
+            store: Arc::new(SqlStore::new(db, sap_system)),
+            batches: BatchCache::default(),
+            nc: NcStager::new(NcConfig::load()?),
+        })
     }
 
-    impl From<csv::Error> for Error {
-        fn from(value: csv::Error) -> Self {
-            log::error!("Casting csv error to app error: {:#?}", value);
-            Self::CsvError
+    /// runs against another store, with NC staging off
+    pub fn with_store(store: Arc<dyn SigmanestStore>) -> Self {
+        Self {
+            store,
+            batches: BatchCache::default(),
+            nc: NcStager::default(),
         }
     }
 }
-
-pub use error::Error;
-pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,324 @@
+//! Staging of NC files to machine queue folders
+//!
+//! When a program is moved to processing, its NC file is copied (or moved) from
+//! the Sigmanest output folder to the machine's queue folder. Cancelling the
+//! program rolls this back.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::{NcConfig, NcStagingMode},
+    Error, Result,
+};
+
+/// An NC file that was staged to a machine queue
+#[derive(Debug, Clone)]
+pub struct StagedNc {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub mode: NcStagingMode,
+    /// the file was already in the queue, so staging it again is not rolled back
+    pub already_staged: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct NcStager {
+    config: NcConfig,
+}
+
+impl NcStager {
+    pub fn new(config: NcConfig) -> Self {
+        if !config.is_enabled() {
+            log::warn!("NC staging is disabled, no NC source folder configured");
+        }
+
+        Self { config }
+    }
+
+    /// builds the source and queue paths of a program's NC file
+    ///
+    /// A machine's `queue` from the machine registry is used instead of the
+    /// configured queue template.
+    fn paths(
+        &self,
+        program: &str,
+        machine: &str,
+        queue: Option<&str>,
+    ) -> Result<Option<(PathBuf, PathBuf)>> {
+        let source = match &self.config.source {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        let queue = queue
+            .or(self.config.queue_template(machine))
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "no NC queue folder configured for machine {}",
+                    machine
+                ))
+            })?;
+
+        let fill = |template: &str| {
+            PathBuf::from(
+                template
+                    .replace("<program>", program)
+                    .replace("<machine>", machine),
+            )
+        };
+
+        Ok(Some((fill(source), fill(queue))))
+    }
+
+    /// copies or moves a program's NC file to the machine's queue
+    ///
+    /// Returns `None` if NC staging is disabled. A file that is already in the
+    /// queue (i.e. staged for another repeat) is reported as `already_staged`.
+    pub async fn stage(
+        &self,
+        program: &str,
+        machine: &str,
+        queue: Option<&str>,
+    ) -> Result<Option<StagedNc>> {
+        let (source, destination) = match self.paths(program, machine, queue)? {
+            Some(paths) => paths,
+            None => return Ok(None),
+        };
+
+        let staged = StagedNc {
+            already_staged: exists(&destination).await,
+            source,
+            destination,
+            mode: self.config.mode,
+        };
+
+        // another repeat of the program may have already moved the file
+        if staged.mode == NcStagingMode::Move
+            && staged.already_staged
+            && !exists(&staged.source).await
+        {
+            log::info!(
+                "NC file for program {} already staged at {:?}",
+                program,
+                staged.destination
+            );
+            return Ok(Some(staged));
+        }
+
+        if !exists(&staged.source).await {
+            return Err(Error::NotFound(format!(
+                "NC file for program {} not found at {:?}",
+                program, staged.source
+            )));
+        }
+
+        if let Some(parent) = staged.destination.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| nc_error("create queue folder", parent, e))?;
+        }
+
+        match staged.mode {
+            NcStagingMode::Copy => copy_file(&staged.source, &staged.destination).await?,
+            NcStagingMode::Move => move_file(&staged.source, &staged.destination).await?,
+        }
+
+        log::info!(
+            "Staged NC file for program {} on {}: {:?} -> {:?} ({:?})",
+            program,
+            machine,
+            staged.source,
+            staged.destination,
+            staged.mode
+        );
+
+        Ok(Some(staged))
+    }
+
+    /// removes a program's NC file from the machine's queue
+    ///
+    /// Moved files are put back in the Sigmanest output folder.
+    pub async fn unstage(&self, program: &str, machine: &str, queue: Option<&str>) -> Result<()> {
+        let (source, destination) = match self.paths(program, machine, queue)? {
+            Some(paths) => paths,
+            None => return Ok(()),
+        };
+
+        if !exists(&destination).await {
+            log::debug!(
+                "NC file for program {} is not staged at {:?}",
+                program,
+                destination
+            );
+            return Ok(());
+        }
+
+        match self.config.mode {
+            NcStagingMode::Copy => tokio::fs::remove_file(&destination)
+                .await
+                .map_err(|e| nc_error("remove", &destination, e))?,
+            NcStagingMode::Move => move_file(&destination, &source).await?,
+        }
+
+        log::info!(
+            "Rolled back NC file for program {} on {}: {:?}",
+            program,
+            machine,
+            destination
+        );
+
+        Ok(())
+    }
+}
+
+async fn exists(path: &Path) -> bool {
+    tokio::fs::try_exists(path).await.unwrap_or(false)
+}
+
+async fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    tokio::fs::copy(from, to)
+        .await
+        .map(|_| ())
+        .map_err(|e| nc_error("copy", from, e))
+}
+
+/// moves a file, copying it if it is moved to a different volume
+async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+
+    copy_file(from, to).await?;
+    tokio::fs::remove_file(from)
+        .await
+        .map_err(|e| nc_error("remove", from, e))
+}
+
+fn nc_error(action: &str, path: &Path, err: std::io::Error) -> Error {
+    log::error!("Failed to {} NC file {:?}: {}", action, path, err);
+
+    Error::NcStaging(format!("failed to {} {:?}", action, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// stager with its source and queue folders in a temporary folder
+    fn stager(mode: NcStagingMode) -> (TempDir, NcStager) {
+        let dir = tempfile::tempdir().unwrap();
+        let template = |path: &str| Some(dir.path().join(path).to_string_lossy().into_owned());
+
+        let config = NcConfig {
+            mode,
+            source: template("output/<program>.nc"),
+            queue: template("queue/<machine>/<program>.nc"),
+            machines: HashMap::new(),
+        };
+
+        (dir, NcStager::new(config))
+    }
+
+    fn write_source(dir: &TempDir, program: &str) -> PathBuf {
+        let path = dir.path().join("output").join(format!("{}.nc", program));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, program).unwrap();
+
+        path
+    }
+
+    #[tokio::test]
+    async fn copy_mode() {
+        let (dir, nc) = stager(NcStagingMode::Copy);
+        let source = write_source(&dir, "50001");
+
+        let staged = nc.stage("50001", "Gemini", None).await.unwrap().unwrap();
+        assert_eq!(staged.destination, dir.path().join("queue/Gemini/50001.nc"));
+        assert_eq!(
+            std::fs::read_to_string(&staged.destination).unwrap(),
+            "50001"
+        );
+        assert!(source.exists());
+        assert!(!staged.already_staged);
+
+        // staging again copies over the queued file
+        let again = nc.stage("50001", "Gemini", None).await.unwrap().unwrap();
+        assert!(again.already_staged);
+
+        nc.unstage("50001", "Gemini", None).await.unwrap();
+        assert!(!staged.destination.exists());
+        assert!(source.exists());
+    }
+
+    #[tokio::test]
+    async fn move_mode() {
+        let (dir, nc) = stager(NcStagingMode::Move);
+        let source = write_source(&dir, "50001");
+
+        let staged = nc.stage("50001", "Gemini", None).await.unwrap().unwrap();
+        assert!(staged.destination.exists());
+        assert!(!source.exists());
+
+        // another repeat of the program finds the file already staged
+        let again = nc.stage("50001", "Gemini", None).await.unwrap().unwrap();
+        assert_eq!(again.destination, staged.destination);
+        assert!(!staged.already_staged);
+        assert!(again.already_staged);
+
+        // unstaging puts the file back
+        nc.unstage("50001", "Gemini", None).await.unwrap();
+        assert!(!staged.destination.exists());
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "50001");
+    }
+
+    #[tokio::test]
+    async fn missing_source() {
+        let (dir, nc) = stager(NcStagingMode::Copy);
+
+        let result = nc.stage("50001", "Gemini", None).await;
+        assert!(matches!(result, Err(Error::NotFound(_))), "{:?}", result);
+        assert!(!dir.path().join("queue").exists());
+
+        // nothing to roll back
+        nc.unstage("50001", "Gemini", None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_queue_folder() {
+        let (dir, nc) = stager(NcStagingMode::Copy);
+        write_source(&dir, "50001");
+
+        // a machine's own queue is created if needed
+        let queue = dir.path().join("titan/new/<program>.nc");
+        let queue = queue.to_string_lossy();
+        let staged = nc
+            .stage("50001", "Titan", Some(&queue))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(staged.destination, dir.path().join("titan/new/50001.nc"));
+        assert!(staged.destination.exists());
+    }
+
+    #[tokio::test]
+    async fn no_queue_configured() {
+        let nc = NcStager::new(NcConfig {
+            source: Some(String::from("output/<program>.nc")),
+            ..Default::default()
+        });
+
+        let result = nc.stage("50001", "Gemini", None).await;
+        assert!(matches!(result, Err(Error::Config(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn disabled() {
+        let nc = NcStager::default();
+
+        assert!(nc.stage("50001", "Gemini", None).await.unwrap().is_none());
+        nc.unstage("50001", "Gemini", None).await.unwrap();
+    }
+}
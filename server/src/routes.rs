@@ -0,0 +1,490 @@
+//! HTTP routes of the interface
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+
+use crate::{
+    batch::{Batch, BatchCandidate, BatchDimensionsReport, BatchDimensionsRow, BatchUpdate},
+    db::api::{
+        FeedbackEntry, FeedbackReport, Machine, MachineUpdate, Nest, ProgramExecution,
+        ProgramState, ProgramSummary,
+    },
+    error,
+    extract::{Json, Path, Query},
+    AppState, Error, Result,
+};
+
+#[derive(Debug, serde::Deserialize)]
+struct ProgramUpdateParams {
+    batch: String,
+    state: ProgramState,
+    #[serde(default)]
+    operator: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgramListParams {
+    /// hide programs if the machine is down
+    #[serde(default)]
+    hide_unavailable: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ReassignParams {
+    machine: String,
+}
+
+/// builds the interface's routes
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(|| async { "root request not implemented yet" }))
+        .route("/health", get(get_health))
+        .route("/machines", get(get_machines))
+        .route("/machine/:machine", get(get_machine).put(update_machine))
+        .route("/batches", get(get_batches).post(create_batch))
+        .route("/batches/dimensions", post(load_batch_dimensions))
+        .route("/batches/:program", get(get_batches_for_program))
+        .route(
+            "/batch/:id",
+            get(get_batch).put(update_batch).delete(retire_batch),
+        )
+        .route("/:machine", get(get_programs))
+        .route("/nest/:nest", get(get_nest).post(update_program))
+        .route("/nest/:nest/history", get(get_program_history))
+        .route(
+            "/nest/:nest/machine",
+            get(get_alternate_machines).post(reassign_program),
+        )
+        .route(
+            "/nest/:nest/:repeat",
+            get(get_nest_repeat).post(update_program_repeat),
+        )
+        .route(
+            "/archive/:id",
+            get(get_nest_by_archive_packet_id).post(update_program_by_archive_packet_id),
+        )
+        .route("/feedback", get(get_feedback))
+        .route("/feedback/:id", delete(delete_feedback))
+        .layer(middleware::from_fn(error::with_correlation_id))
+        .with_state(state)
+}
+
+async fn get_health(State(state): State<Arc<AppState>>) -> Result<StatusCode> {
+    match state.store.is_available() {
+        true => Ok(StatusCode::OK),
+        false => Err(Error::SqlPoolError),
+    }
+}
+
+async fn get_machines(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<Vec<Machine>>)> {
+    log::debug!("Requested machines list");
+
+    let machines = state.store.get_machines().await?;
+
+    Ok((StatusCode::OK, Json(machines)))
+}
+
+async fn get_machine(
+    State(state): State<Arc<AppState>>,
+    Path(machine): Path<String>,
+) -> Result<(StatusCode, Json<Machine>)> {
+    log::debug!("Requested machine {}", machine);
+
+    let machine = state.store.get_machine(&machine).await?;
+
+    Ok((StatusCode::OK, Json(machine)))
+}
+
+/// adds a machine to the registry, or updates its capabilities and status
+async fn update_machine(
+    State(state): State<Arc<AppState>>,
+    Path(machine): Path<String>,
+    Json(update): Json<MachineUpdate>,
+) -> Result<(StatusCode, Json<Machine>)> {
+    log::info!("Updating machine {}", machine);
+
+    let machine = state.store.update_machine(&machine, update).await?;
+
+    Ok((StatusCode::OK, Json(machine)))
+}
+
+async fn get_batches(State(state): State<Arc<AppState>>) -> Result<(StatusCode, Json<Vec<Batch>>)> {
+    log::debug!("Requested batches list");
+
+    let batches = state.batches.get(state.store.as_ref()).await?;
+
+    Ok((StatusCode::OK, Json(batches)))
+}
+
+async fn get_batch(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Batch>)> {
+    log::debug!("Requested batch {}", id);
+
+    let batch = state.store.get_batch(&id).await?;
+
+    Ok((StatusCode::OK, Json(batch)))
+}
+
+async fn create_batch(
+    State(state): State<Arc<AppState>>,
+    Json(batch): Json<Batch>,
+) -> Result<(StatusCode, Json<Batch>)> {
+    log::info!("Creating batch {}", batch.id);
+
+    state.store.create_batch(&batch).await?;
+    state.batches.invalidate().await;
+
+    Ok((StatusCode::CREATED, Json(batch)))
+}
+
+async fn update_batch(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(update): Json<BatchUpdate>,
+) -> Result<(StatusCode, Json<Batch>)> {
+    log::info!("Updating batch {}", id);
+
+    let batch = state.store.update_batch(&id, update).await?;
+    state.batches.invalidate().await;
+
+    Ok((StatusCode::OK, Json(batch)))
+}
+
+async fn retire_batch(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    log::info!("Retiring batch {}", id);
+
+    state.store.retire_batch(&id).await?;
+    state.batches.invalidate().await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// sets actual dimensions of non-standard batches, reporting rows that were rejected
+async fn load_batch_dimensions(
+    State(state): State<Arc<AppState>>,
+    Json(rows): Json<Vec<BatchDimensionsRow>>,
+) -> Result<(StatusCode, Json<BatchDimensionsReport>)> {
+    log::info!("Loading actual dimensions for {} batches", rows.len());
+
+    let report = state.store.load_batch_dimensions(rows).await?;
+    state.batches.invalidate().await;
+
+    let status = match report.rejected.is_empty() {
+        true => StatusCode::OK,
+        false => StatusCode::MULTI_STATUS,
+    };
+
+    Ok((status, Json(report)))
+}
+
+/// gets the batches that can be used for a program, best match first
+async fn get_batches_for_program(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+) -> Result<(StatusCode, Json<Vec<BatchCandidate>>)> {
+    log::debug!("Requested batches list for program `{}`", program);
+
+    let batches = state.batches.get(state.store.as_ref()).await?;
+    let repeat_id = state.store.current_repeat(&program).await?;
+    let candidates = state
+        .store
+        .get_batch_candidates(batches, &program, repeat_id)
+        .await?;
+
+    Ok((StatusCode::OK, Json(candidates)))
+}
+
+async fn get_feedback(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<FeedbackReport<FeedbackEntry<Nest>>>)> {
+    log::debug!("Requested feedback");
+
+    let feedback = state.store.get_feedback().await?;
+
+    Ok((StatusCode::OK, Json(feedback)))
+}
+
+/// deletes exported feedback, so it is not exported again
+async fn delete_feedback(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    log::info!("Deleting feedback with ArchivePacketID {}", id);
+
+    state.store.delete_feedback(id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_programs(
+    State(state): State<Arc<AppState>>,
+    Path(machine): Path<String>,
+    Query(params): Query<ProgramListParams>,
+) -> Result<(StatusCode, Json<Vec<ProgramSummary>>)> {
+    log::debug!("Requested programs for machine {}", machine);
+
+    let programs = state
+        .store
+        .get_programs(&machine, params.hide_unavailable)
+        .await?;
+
+    Ok((StatusCode::OK, Json(programs)))
+}
+
+/// gets the machines a program can be moved to
+async fn get_alternate_machines(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+) -> Result<(StatusCode, Json<Vec<Machine>>)> {
+    log::debug!("Requested alternate machines for program {}", program);
+
+    let machines = state.store.get_alternate_machines(&program).await?;
+
+    Ok((StatusCode::OK, Json(machines)))
+}
+
+/// moves a program to an alternate machine
+async fn reassign_program(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+    Json(params): Json<ReassignParams>,
+) -> Result<(StatusCode, Json<Machine>)> {
+    log::info!("Moving program {} to machine {}", program, params.machine);
+
+    let machine = state
+        .store
+        .reassign_program(&program, &params.machine)
+        .await?;
+
+    Ok((StatusCode::OK, Json(machine)))
+}
+
+/// gets the repeat of a program that is in progress, or the next one to run
+async fn get_nest(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+) -> Result<(StatusCode, Json<Nest>)> {
+    log::debug!("Requested program {}", program);
+
+    let repeat_id = state.store.current_repeat(&program).await?;
+    let nest = state.store.get_nest(&program, repeat_id).await?;
+
+    Ok((StatusCode::OK, Json(nest)))
+}
+
+async fn get_nest_repeat(
+    State(state): State<Arc<AppState>>,
+    Path((program, repeat_id)): Path<(String, i32)>,
+) -> Result<(StatusCode, Json<Nest>)> {
+    log::debug!("Requested program {} repeat {}", program, repeat_id);
+
+    let nest = state.store.get_nest(&program, repeat_id).await?;
+
+    Ok((StatusCode::OK, Json(nest)))
+}
+
+async fn get_nest_by_archive_packet_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<Nest>)> {
+    log::debug!("Requested program with ArchivePacketID {}", id);
+
+    let nest = state.store.get_nest_by_archive_packet_id(id).await?;
+
+    Ok((StatusCode::OK, Json(nest)))
+}
+
+async fn get_program_history(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+) -> Result<(StatusCode, Json<Vec<ProgramExecution>>)> {
+    log::debug!("Requested execution history of program {}", program);
+
+    let history = state.store.get_history(&program).await?;
+
+    Ok((StatusCode::OK, Json(history)))
+}
+
+/// updates the repeat of a program that is in progress, or starts the next one
+async fn update_program(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+    Json(params): Json<ProgramUpdateParams>,
+) -> Result<(StatusCode, Json<ProgramExecution>)> {
+    let repeat_id = state.store.current_repeat(&program).await?;
+
+    transition_program(&state, &program, repeat_id, params).await
+}
+
+async fn update_program_repeat(
+    State(state): State<Arc<AppState>>,
+    Path((program, repeat_id)): Path<(String, i32)>,
+    Json(params): Json<ProgramUpdateParams>,
+) -> Result<(StatusCode, Json<ProgramExecution>)> {
+    transition_program(&state, &program, repeat_id, params).await
+}
+
+async fn update_program_by_archive_packet_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(params): Json<ProgramUpdateParams>,
+) -> Result<(StatusCode, Json<ProgramExecution>)> {
+    let nest = state.store.get_nest_by_archive_packet_id(id).await?;
+
+    transition_program(
+        &state,
+        &nest.program.program_name,
+        nest.program.repeat_id,
+        params,
+    )
+    .await
+}
+
+async fn transition_program(
+    state: &AppState,
+    program: &str,
+    repeat_id: i32,
+    params: ProgramUpdateParams,
+) -> Result<(StatusCode, Json<ProgramExecution>)> {
+    let batch = Some(params.batch.as_str()).filter(|b| !b.is_empty());
+
+    match params.state {
+        ProgramState::Initiated => {
+            log::trace!("Program {} repeat {} initiated", program, repeat_id)
+        }
+        ProgramState::Processing => {
+            log::trace!(
+                "Program {} repeat {} is moved to processing with batch {}",
+                program,
+                repeat_id,
+                params.batch
+            );
+        }
+        ProgramState::Complete => log::info!(
+            "Program {} repeat {} complete with batch {}",
+            program,
+            repeat_id,
+            params.batch
+        ),
+        ProgramState::Cancelled => {
+            log::trace!("Program {} repeat {} cancelled", program, repeat_id)
+        }
+    }
+
+    // a program can only be processed with a batch it qualifies for
+    match (params.state, batch) {
+        (ProgramState::Processing, Some(id)) => {
+            state
+                .store
+                .check_batch_assignable(id, program, repeat_id)
+                .await?;
+        }
+        (ProgramState::Processing, None) => {
+            return Err(Error::Validation(format!(
+                "A batch is required to process program {} repeat {}",
+                program, repeat_id
+            )));
+        }
+        _ => (),
+    }
+
+    let previous = state
+        .store
+        .get_latest_execution(program, repeat_id)
+        .await?
+        .map(|exec| exec.state);
+    let machine = state
+        .store
+        .get_program(program, repeat_id)
+        .await?
+        .machine_name;
+    let queue = state.store.get_nc_queue(&machine).await?;
+
+    // NC file goes to the machine queue before the program is processing,
+    //  and is rolled back if the state change is rejected, unless it was
+    //  already queued (e.g. the repeat is already processing)
+    let staged = match params.state {
+        ProgramState::Processing => state.nc.stage(program, &machine, queue.as_deref()).await?,
+        _ => None,
+    };
+
+    let execution = state
+        .store
+        .transition(
+            program,
+            repeat_id,
+            params.state,
+            batch,
+            params.operator.as_deref(),
+        )
+        .await;
+    let execution = match execution {
+        Ok(execution) => execution,
+        Err(e) => {
+            if staged.is_some_and(|staged| !staged.already_staged) {
+                unstage_nc(state, program, repeat_id, &machine, queue.as_deref()).await;
+            }
+            return Err(e);
+        }
+    };
+
+    // going back from processing takes the NC file off the machine queue
+    if matches!(
+        params.state,
+        ProgramState::Initiated | ProgramState::Cancelled
+    ) && previous == Some(ProgramState::Processing)
+    {
+        unstage_nc(state, program, repeat_id, &machine, queue.as_deref()).await;
+    }
+
+    Ok((StatusCode::CREATED, Json(execution)))
+}
+
+/// rolls back a program's NC file staging, unless another repeat is using it
+///
+/// Failures are only logged, since the program state has already been decided.
+async fn unstage_nc(
+    state: &AppState,
+    program: &str,
+    repeat_id: i32,
+    machine: &str,
+    queue: Option<&str>,
+) {
+    match state
+        .store
+        .other_repeats_processing(program, repeat_id)
+        .await
+    {
+        Ok(false) => (),
+        Ok(true) => {
+            log::info!(
+                "Leaving NC file for program {} staged for other repeats",
+                program
+            );
+            return;
+        }
+        Err(e) => {
+            log::error!("Failed to check repeats of program {}: {}", program, e);
+            return;
+        }
+    }
+
+    if let Err(e) = state.nc.unstage(program, machine, queue).await {
+        log::error!("Failed to roll back NC file of program {}: {}", program, e);
+    }
+}
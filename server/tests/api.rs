@@ -0,0 +1,621 @@
+//! Runs the HTTP API against a snapshot, with no database
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+use sigmanest_interface::{
+    config::{NcConfig, NcStagingMode},
+    db::{
+        api::ProgramState,
+        store::{
+            MemoryStore, SigmanestStore, SimTransaction, Snapshot, REMNANT_DXF_TEMPLATE,
+            SIM_TRANS_DISTRICTS,
+        },
+    },
+    nc::NcStager,
+    routes, AppState, Error,
+};
+
+const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/snapshot.json");
+
+struct TestApp {
+    store: Arc<MemoryStore>,
+    state: Arc<AppState>,
+}
+
+impl TestApp {
+    fn new() -> Self {
+        Self::for_sap_system("QAS")
+    }
+
+    /// app pushing SimTrans transactions for a SAP system
+    fn for_sap_system(sap_system: &str) -> Self {
+        let snapshot = Snapshot::load(SNAPSHOT).expect("failed to load snapshot");
+        let store = Arc::new(MemoryStore::new(snapshot).with_sap_system(sap_system));
+        let state = Arc::new(AppState::with_store(store.clone()));
+
+        Self { store, state }
+    }
+
+    /// app with NC files staged from and to a temporary folder
+    fn with_nc(mode: NcStagingMode) -> (TempDir, Self) {
+        let dir = tempfile::tempdir().unwrap();
+        let template = |path: &str| Some(dir.path().join(path).to_string_lossy().into_owned());
+        let config = NcConfig {
+            mode,
+            source: template("output/<program>.nc"),
+            queue: template("queue/<machine>/<program>.nc"),
+            machines: HashMap::new(),
+        };
+
+        let app = Self::new();
+        let state = Arc::new(AppState {
+            nc: NcStager::new(config),
+            ..AppState::with_store(app.store.clone())
+        });
+
+        (dir, Self { state, ..app })
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        let request = match body {
+            Some(body) => request.body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = routes::router(self.state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body = match bytes.is_empty() {
+            true => Value::Null,
+            false => serde_json::from_slice(&bytes).unwrap(),
+        };
+
+        (status, body)
+    }
+
+    async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, uri, None).await
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    async fn put(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::PUT, uri, Some(body)).await
+    }
+
+    async fn delete(&self, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::DELETE, uri, None).await
+    }
+}
+
+fn names(list: &Value, key: &str) -> Vec<String> {
+    list.as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item[key].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn health() {
+    let app = TestApp::new();
+
+    let (status, _) = app.get("/health").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rejected_requests() {
+    let app = TestApp::new();
+
+    let (status, error) = app.post("/batches", json!({ "id": "B100" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["code"], "INVALID_REQUEST");
+    assert!(error["correlationId"].is_string());
+
+    let (status, error) = app.get("/nest/50001/first").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], "INVALID_REQUEST");
+}
+
+#[tokio::test]
+async fn correlation_ids() {
+    let app = TestApp::new();
+
+    let correlation_id = |id: &str| {
+        let request = Request::builder()
+            .uri("/machine/Nobody")
+            .header("x-correlation-id", id)
+            .body(Body::empty())
+            .unwrap();
+
+        async {
+            let response = routes::router(app.state.clone())
+                .oneshot(request)
+                .await
+                .unwrap();
+
+            response.headers()["x-correlation-id"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+
+    assert_eq!(correlation_id("sap-1234.5").await, "sap-1234.5");
+
+    // ids that are too long or not safe to log are replaced
+    let long = "a".repeat(100);
+    assert_ne!(correlation_id(&long).await, long);
+    let forged = "1234 ERROR [other-id] forged";
+    assert_ne!(correlation_id(forged).await, forged);
+}
+
+#[tokio::test]
+async fn machines_and_programs() {
+    let app = TestApp::new();
+
+    let (status, machines) = app.get("/machines").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&machines, "name"), ["Gemini", "Titan"]);
+    assert_eq!(machines[0]["status"], "Up");
+
+    let (status, programs) = app.get("/Gemini").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        programs,
+        json!([{ "program": "50001", "cuttingTime": 12.5, "repeats": 2 }])
+    );
+
+    let (status, error) = app.get("/machine/Nobody").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["code"], "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn nests() {
+    let app = TestApp::new();
+
+    let (status, nest) = app.get("/nest/50001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(nest["archivePacketId"], 1001);
+    assert_eq!(nest["program"]["repeatId"], 1);
+    assert_eq!(nest["program"]["machineName"], "Gemini");
+    assert_eq!(
+        nest["sheet"],
+        json!({ "sheetName": "50W-0500", "materialMaster": "50W-0500" })
+    );
+    assert_eq!(
+        names(&nest["parts"], "partName"),
+        ["1200055A-X1", "1200055A-X2"]
+    );
+    assert_eq!(
+        nest["parts"][0],
+        json!({
+            "partName": "1200055A-X1",
+            "partQty": 4,
+            "job": "1200055A",
+            "shipment": 1,
+            "trueArea": 210.25,
+            "nestedArea": 256.0
+        })
+    );
+    assert_eq!(names(&nest["remnants"], "remnantName"), ["R-0002"]);
+
+    let (status, nest) = app.get("/archive/2001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(nest["program"]["programName"], "50002");
+    assert_eq!(nest["sheet"]["sheetName"], "R-0001");
+
+    let (status, _) = app.get("/nest/50001/3").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn feedback() {
+    let app = TestApp::new();
+
+    // the row with an unknown transaction is skipped, and reported
+    let (status, report) = app.get("/feedback").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["skipped"].as_array().unwrap().len(), 1);
+    assert_eq!(report["skipped"][0]["archivePacketId"], 995);
+    assert!(report["skipped"][0]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("STPrgArc"));
+
+    let feedback = &report["feedback"];
+    let ids: Vec<i64> = feedback
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["archivePacketId"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, [2001, 990, 1001]);
+    assert_eq!(feedback[1]["state"], "deleted");
+
+    let nest = &feedback[2]["state"]["created"];
+    assert_eq!(nest["program"]["cuttingTime"], 12.5);
+    assert_eq!(
+        names(&nest["parts"], "partName"),
+        ["1200055A-X1", "1200055A-X2"]
+    );
+    assert_eq!(names(&nest["remnants"], "remnantName"), ["R-0002"]);
+
+    let (status, _) = app.delete("/feedback/1001").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, report) = app.get("/feedback").await;
+    assert_eq!(report["feedback"].as_array().unwrap().len(), 2);
+
+    let (status, _) = app.delete("/feedback/1001").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn batches() {
+    let app = TestApp::new();
+
+    let batch = json!({
+        "id": "B100",
+        "mm": "50W-0500",
+        "sheetName": "50W-0500",
+        "type": "New",
+    });
+    let (status, _) = app.post("/batches", batch.clone()).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = app.post("/batches", batch).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, error) = app
+        .post(
+            "/batches",
+            json!({ "id": "B200", "mm": "A572-0250", "sheetName": "A572-0250", "type": "New" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+
+    let (status, candidates) = app.get("/batches/50001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&candidates, "id"), ["B100"]);
+    assert_eq!(candidates[0]["match"], "Sheet");
+
+    let (status, _) = app
+        .post(
+            "/batches",
+            json!({ "id": "B300", "mm": "50W-0375", "sheetName": "R-0001", "type": "Remnant" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // standard batches have no actual dimensions, and dimensions can be numbers
+    let (status, report) = app
+        .post(
+            "/batches/dimensions",
+            json!([
+                { "batch": "B100", "sheetName": "50W-0500", "actualWidth": "95.5", "actualLength": "" },
+                { "batch": "B300", "sheetName": "R-0001", "actualWidth": "47.5", "actualLength": 59 },
+                { "batch": "B999", "sheetName": "50W-0500", "actualWidth": "95.5", "actualLength": "" },
+            ]),
+        )
+        .await;
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+    assert_eq!(names(&report["updated"], "id"), ["B300"]);
+    assert_eq!(report["updated"][0]["actualWidth"], 47.5);
+    assert_eq!(report["updated"][0]["actualLength"], 59.0);
+    assert_eq!(names(&report["rejected"], "batch"), ["B100", "B999"]);
+
+    let (status, _) = app.put("/batch/B100", json!({ "actualWidth": 95.5 })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // null clears an override, missing fields are left as is
+    let (status, batch) = app.put("/batch/B300", json!({ "actualWidth": null })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(batch["actualWidth"], Value::Null);
+    assert_eq!(batch["actualLength"], 59.0);
+
+    for id in ["B100", "B300"] {
+        let (status, _) = app.delete(&format!("/batch/{}", id)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let (_, batches) = app.get("/batches").await;
+    assert_eq!(batches, json!([]));
+}
+
+#[tokio::test]
+async fn program_transitions() {
+    let app = TestApp::new();
+
+    let batch = json!({
+        "id": "B100",
+        "mm": "50W-0500",
+        "sheetName": "50W-0500",
+        "type": "New",
+    });
+    let (status, _) = app.post("/batches", batch).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // processing needs a batch
+    let (status, _) = app
+        .post("/nest/50001", json!({ "batch": "", "state": "Processing" }))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    for state in ["Initiated", "Processing", "Complete"] {
+        let (status, execution) = app
+            .post(
+                "/nest/50001",
+                json!({ "batch": "B100", "state": state, "operator": "jdoe" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{}", execution);
+        assert_eq!(execution["repeatId"], 1);
+        assert_eq!(execution["state"], state);
+    }
+
+    let (_, history) = app.get("/nest/50001/history").await;
+    assert_eq!(
+        names(&history, "state"),
+        ["Initiated", "Processing", "Complete"]
+    );
+
+    assert_eq!(
+        app.store.transactions(),
+        [SimTransaction {
+            trans_type: String::from("SN70"),
+            district: 1,
+            program_name: Some(String::from("50001")),
+            program_repeat: Some(1),
+            item_name: None,
+            file_name: None,
+        }]
+    );
+
+    // the completed repeat is no longer listed
+    let (_, programs) = app.get("/Gemini").await;
+    assert_eq!(programs[0]["repeats"], 1);
+
+    let (_, nest) = app.get("/nest/50001").await;
+    assert_eq!(nest["program"]["repeatId"], 2);
+
+    // the batch is consumed by repeat 1
+    app.post("/nest/50001", json!({ "batch": "", "state": "Initiated" }))
+        .await;
+    let (status, _) = app
+        .post(
+            "/nest/50001",
+            json!({ "batch": "B100", "state": "Processing" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn sim_trans_district() {
+    for (sap_system, district) in SIM_TRANS_DISTRICTS {
+        let app = TestApp::for_sap_system(sap_system);
+
+        let batch = json!({
+            "id": "B100",
+            "mm": "50W-0500",
+            "sheetName": "50W-0500",
+            "type": "New",
+        });
+        app.post("/batches", batch).await;
+        for state in ["Initiated", "Processing", "Complete"] {
+            let (status, _) = app
+                .post("/nest/50001", json!({ "batch": "B100", "state": state }))
+                .await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+
+        let transactions = app.store.transactions();
+        assert_eq!(transactions[0].trans_type, "SN70");
+        assert_eq!(transactions[0].district, district, "{}", sap_system);
+    }
+
+    // no SimTrans transaction without a SapInterfaceConfig row
+    let app = TestApp::for_sap_system("XYZ");
+    let batch = json!({
+        "id": "B100",
+        "mm": "50W-0500",
+        "sheetName": "50W-0500",
+        "type": "New",
+    });
+    app.post("/batches", batch).await;
+    for state in ["Initiated", "Processing"] {
+        app.post("/nest/50001", json!({ "batch": "B100", "state": state }))
+            .await;
+    }
+    let (status, _) = app
+        .post("/nest/50001", json!({ "batch": "", "state": "Complete" }))
+        .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(app.store.transactions().is_empty());
+
+    let (_, history) = app.get("/nest/50001/history").await;
+    assert_eq!(names(&history, "state"), ["Initiated", "Processing"]);
+}
+
+#[tokio::test]
+async fn repeated_processing_keeps_nc_file() {
+    for mode in [NcStagingMode::Copy, NcStagingMode::Move] {
+        let (dir, app) = TestApp::with_nc(mode);
+        let output = dir.path().join("output");
+        std::fs::create_dir_all(&output).unwrap();
+        std::fs::write(output.join("50001.nc"), "50001").unwrap();
+        let queued = dir.path().join("queue/Gemini/50001.nc");
+
+        let batch = json!({
+            "id": "B100",
+            "mm": "50W-0500",
+            "sheetName": "50W-0500",
+            "type": "New",
+        });
+        app.post("/batches", batch).await;
+        app.post("/nest/50001", json!({ "batch": "", "state": "Initiated" }))
+            .await;
+
+        let processing = json!({ "batch": "B100", "state": "Processing" });
+        let (status, execution) = app.post("/nest/50001", processing.clone()).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", execution);
+        assert!(queued.exists(), "{:?}", mode);
+
+        // the repeat is already processing, so its NC file stays queued
+        let (status, _) = app.post("/nest/50001", processing).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", mode);
+        assert!(queued.exists(), "{:?}", mode);
+
+        let (_, history) = app.get("/nest/50001/history").await;
+        assert_eq!(names(&history, "state"), ["Initiated", "Processing"]);
+    }
+}
+
+#[tokio::test]
+async fn remnant_dimensions() {
+    let app = TestApp::new();
+
+    let batch = json!({
+        "id": "B300",
+        "mm": "50W-0375",
+        "sheetName": "R-0001",
+        "type": "Remnant",
+        "actualLength": 59.0,
+    });
+    let (status, _) = app.post("/batches", batch).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = app
+        .post(
+            "/batches",
+            json!({ "id": "B301", "mm": "50W-0375", "sheetName": "S-0375", "type": "New" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // R-0001 is a remnant in stock, so only its own batch qualifies
+    let (status, candidates) = app.get("/batches/50002").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&candidates, "id"), ["B300"]);
+
+    for state in ["Initiated", "Processing"] {
+        let (status, execution) = app
+            .post("/nest/50002", json!({ "batch": "B300", "state": state }))
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{}", execution);
+    }
+
+    // the remnant is updated with its geometry
+    assert_eq!(
+        app.store.transactions(),
+        [SimTransaction {
+            trans_type: String::from("SN97"),
+            district: 1,
+            program_name: None,
+            program_repeat: None,
+            item_name: Some(String::from("R-0001")),
+            file_name: Some(REMNANT_DXF_TEMPLATE.replace("<sheet_name>", "R-0001")),
+        }]
+    );
+
+    // a failed assignment leaves the state change out as well
+    app.post("/nest/50001", json!({ "batch": "", "state": "Initiated" }))
+        .await;
+    let result = app
+        .store
+        .transition("50001", 1, ProgramState::Processing, Some("B300"), None)
+        .await;
+    assert!(matches!(result, Err(Error::Conflict(_))), "{:?}", result);
+
+    let (_, history) = app.get("/nest/50001/history").await;
+    assert_eq!(names(&history, "state"), ["Initiated"]);
+    assert_eq!(app.store.transactions().len(), 1);
+}
+
+#[tokio::test]
+async fn program_reassignment() {
+    let app = TestApp::new();
+
+    // a registered machine does not need a process, if the program's machine has none
+    let (status, machine) = app.put("/machine/Gemini", json!({ "status": "Up" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(machine["registered"], true);
+    let (_, machines) = app.get("/nest/50002/machine").await;
+    assert_eq!(names(&machines, "name"), ["Gemini"]);
+
+    for machine in ["Gemini", "Titan"] {
+        let (status, _) = app
+            .put(
+                &format!("/machine/{}", machine),
+                json!({ "process": "Plasma", "maxWidth": 120.0, "maxLength": 480.0 }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, machines) = app.get("/nest/50002/machine").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&machines, "name"), ["Gemini"]);
+
+    let (status, machine) = app
+        .post("/nest/50002/machine", json!({ "machine": "Gemini" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(machine["name"], "Gemini");
+
+    let (_, programs) = app.get("/Gemini").await;
+    assert_eq!(names(&programs, "program"), ["50001", "50002"]);
+    let (_, programs) = app.get("/Titan").await;
+    assert_eq!(programs, json!([]));
+}
+
+#[tokio::test]
+async fn machine_updates() {
+    let app = TestApp::new();
+
+    let (_, machine) = app.get("/machine/Titan").await;
+    assert_eq!(machine["registered"], false);
+
+    let (status, _) = app
+        .put(
+            "/machine/Titan",
+            json!({ "process": "Plasma", "maxWidth": 120.0, "ncQueue": "queue/titan" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // null clears a field, missing fields are left as is
+    let (status, machine) = app
+        .put(
+            "/machine/Titan",
+            json!({ "maxWidth": null, "ncQueue": null }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(machine["process"], "Plasma");
+    assert_eq!(machine["maxWidth"], Value::Null);
+    assert_eq!(machine["ncQueue"], Value::Null);
+    assert_eq!(machine["registered"], true);
+}
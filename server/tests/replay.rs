@@ -0,0 +1,128 @@
+//! Replays feedback export and nest lookups against `scripts/snapshot.py` dumps
+//!
+//! The rows the queries would read are taken from the snapshot, and assembled
+//! by the same functions as `export_feedback` and `Nest::get`.
+//!
+//! Each `<name>.json` snapshot in `tests/fixtures` is compared with its
+//! `<name>.golden.json`. After an intended change, rerun with `UPDATE_GOLDEN=1`
+//! to rewrite the golden files and review their diff.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use sigmanest_interface::db::{
+    api::{FeedbackEntry, FeedbackReport, Nest, Part, Program, Remnant},
+    exports::feedback_from_rows,
+    store::Snapshot,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Everything read from a snapshot, in the shapes the API returns
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Replay {
+    programs: Vec<Program>,
+    program_feedback: FeedbackReport<FeedbackEntry<Program>>,
+    part_feedback: FeedbackReport<FeedbackEntry<Part>>,
+    complete_part_feedback: FeedbackReport<Part>,
+    future_remnants: Vec<(String, i32, Remnant)>,
+    feedback: FeedbackReport<FeedbackEntry<Nest>>,
+    nests: Vec<ReplayedNest>,
+}
+
+/// A nest lookup, with the error message if it failed
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayedNest {
+    program_name: String,
+    repeat_id: i32,
+    nest: Result<Nest, String>,
+}
+
+fn replay(path: &Path) -> Replay {
+    let snapshot = Snapshot::load(path).unwrap();
+
+    let programs = snapshot.programs().unwrap();
+    let program_feedback = snapshot.program_feedback().unwrap();
+    let part_feedback = snapshot.part_feedback().unwrap();
+    let complete_part_feedback = snapshot.complete_part_feedback().unwrap();
+    let future_remnants = snapshot.future_remnants().unwrap();
+
+    let feedback = feedback_from_rows(snapshot.feedback_rows().unwrap()).unwrap();
+
+    let mut nests = Vec::new();
+    for program in &programs {
+        let rows = snapshot.nest_rows(&program.program_name, program.repeat_id);
+        let nest = Nest::from_rows(&program.program_name, program.repeat_id, rows)
+            .map_err(|e| e.to_string());
+
+        nests.push(ReplayedNest {
+            program_name: program.program_name.clone(),
+            repeat_id: program.repeat_id,
+            nest,
+        });
+    }
+
+    Replay {
+        programs,
+        program_feedback,
+        part_feedback,
+        complete_part_feedback,
+        future_remnants,
+        feedback,
+        nests,
+    }
+}
+
+fn snapshots() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(FIXTURES)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.ends_with(".json") && !name.ends_with(".golden.json")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+}
+
+#[test]
+fn snapshots_match_golden_files() {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok_and(|val| val == "1");
+
+    let paths = snapshots();
+    assert!(!paths.is_empty(), "no snapshots in {}", FIXTURES);
+
+    for path in paths {
+        let golden = path.with_extension("golden.json");
+        let actual = serde_json::to_value(replay(&path)).unwrap();
+
+        if update {
+            let json = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+            std::fs::write(&golden, json).unwrap();
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&golden).unwrap_or_else(|e| {
+            panic!(
+                "failed to read {} ({}), run with UPDATE_GOLDEN=1 to create it",
+                golden.display(),
+                e
+            )
+        });
+        let expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+
+        // compared as JSON, so line endings of the golden file do not matter
+        assert!(
+            actual == expected,
+            "replay of {} does not match {}, run with UPDATE_GOLDEN=1 and review the diff\n{:#}",
+            path.display(),
+            golden.display(),
+            actual
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Database connection and NC staging configuration
+//!
+//! Database settings are layered, with later layers overriding earlier ones:
+//!  1. defaults (development database)
+//!  2. `[database]` table of the config file
+//!  3. `[profiles.<name>]` table of the config file for the selected profile
+//!  4. environment variables (`SNDB_*`)
+//!
+//! The config file is `sndb.toml` in the working directory, or the path in `SNDB_CONFIG`.
+//! The profile is selected with `SNDB_PROFILE` or the `profile` key of the config file.
+//! Profiles are named after the `SapSystem` rows in `dbo.SapInterfaceConfig` (QAS, PRD, DEV),
+//! and the SAP interface uses the profile name as the SAP system unless `SAP_SYSTEM` is set.
+//!
+//! NC staging is set in the `[nc]` table of the config file, and can be overridden
+//! with `SNDB_NC_MODE`, `SNDB_NC_SOURCE` and `SNDB_NC_QUEUE`.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{Error, Result};
+
+const CONFIG_FILE: &str = "sndb.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthKind {
+    /// SQL Server authentication with user and password
+    Sql,
+    /// Windows (or kerberos) authentication
+    Integrated,
+}
+
+/// Database settings as read from a single layer; unset values fall through to
+/// the previous layer
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct DbSettings {
+    pub host: Option<String>,
+    pub instance: Option<String>,
+    pub port: Option<u16>,
+    pub database: Option<String>,
+    pub auth: Option<AuthKind>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub trust_cert: Option<bool>,
+    pub pool_size: Option<u32>,
+    /// seconds to wait for a database connection
+    pub connection_timeout: Option<u64>,
+}
+
+impl DbSettings {
+    /// overrides any settings in `self` that are set in `other`
+    fn merge(self, other: Self) -> Self {
+        Self {
+            host: other.host.or(self.host),
+            instance: other.instance.or(self.instance),
+            port: other.port.or(self.port),
+            database: other.database.or(self.database),
+            auth: other.auth.or(self.auth),
+            user: other.user.or(self.user),
+            password: other.password.or(self.password),
+            trust_cert: other.trust_cert.or(self.trust_cert),
+            pool_size: other.pool_size.or(self.pool_size),
+            connection_timeout: other.connection_timeout.or(self.connection_timeout),
+        }
+    }
+
+    fn from_env() -> Result<Self> {
+        let var = |key: &str| std::env::var(key).ok().filter(|val| !val.is_empty());
+        let parse = |key: &str| -> Result<Option<u32>> {
+            var(key)
+                .map(|val| {
+                    val.parse()
+                        .map_err(|_| Error::Config(format!("{} must be a number: `{}`", key, val)))
+                })
+                .transpose()
+        };
+
+        let auth = match var("SNDB_AUTH").as_deref() {
+            None => None,
+            Some("sql") => Some(AuthKind::Sql),
+            Some("integrated") => Some(AuthKind::Integrated),
+            Some(val) => {
+                return Err(Error::Config(format!(
+                    "SNDB_AUTH must be `sql` or `integrated`: `{}`",
+                    val
+                )))
+            }
+        };
+
+        Ok(Self {
+            host: var("SNDB_HOST"),
+            instance: var("SNDB_INSTANCE"),
+            port: parse("SNDB_PORT")?
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| Error::Config(String::from("SNDB_PORT out of range")))?,
+            database: var("SNDB_DATABASE"),
+            auth,
+            user: var("SNDB_USER"),
+            password: var("SNDB_PWD"),
+            trust_cert: var("SNDB_TRUST_CERT").map(|val| matches!(val.as_str(), "1" | "true")),
+            pool_size: parse("SNDB_POOL_SIZE")?,
+            connection_timeout: parse("SNDB_CONNECTION_TIMEOUT")?.map(u64::from),
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    profile: Option<String>,
+    #[serde(default)]
+    database: DbSettings,
+    #[serde(default)]
+    profiles: HashMap<String, DbSettings>,
+    #[serde(default)]
+    nc: NcConfig,
+}
+
+impl ConfigFile {
+    fn load() -> Result<Self> {
+        let (path, required) = match std::env::var("SNDB_CONFIG") {
+            Ok(path) => (path, true),
+            Err(_) => (String::from(CONFIG_FILE), false),
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                log::debug!("using config file `{}`", path);
+                toml::from_str(&contents)
+                    .map_err(|e| Error::Config(format!("failed to parse `{}`: {}", path, e)))
+            }
+            Err(_) if !required => Ok(Self::default()),
+            Err(e) => Err(Error::Config(format!("failed to read `{}`: {}", path, e))),
+        }
+    }
+}
+
+/// Resolved database connection config
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Name of the profile used, if any
+    pub profile: Option<String>,
+    pub host: String,
+    pub instance: Option<String>,
+    pub port: Option<u16>,
+    pub database: String,
+    pub auth: AuthKind,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub trust_cert: bool,
+    pub pool_size: u32,
+    pub connection_timeout: std::time::Duration,
+}
+
+impl DbConfig {
+    /// loads config from the config file and environment
+    pub fn load() -> Result<Self> {
+        let file = ConfigFile::load()?;
+        let profile = std::env::var("SNDB_PROFILE").ok().or(file.profile);
+
+        let mut settings = file.database;
+        if let Some(name) = &profile {
+            match file.profiles.get(name) {
+                Some(profile) => settings = settings.merge(profile.clone()),
+                None => {
+                    return Err(Error::Config(format!(
+                        "profile `{}` not found in config file",
+                        name
+                    )))
+                }
+            }
+        }
+        settings = settings.merge(DbSettings::from_env()?);
+
+        Ok(Self {
+            profile,
+            host: settings.host.unwrap_or_else(|| String::from("HIISQLSERV6")),
+            instance: settings.instance,
+            port: settings.port,
+            database: settings
+                .database
+                .unwrap_or_else(|| String::from("SNDBaseISap")),
+            auth: settings.auth.unwrap_or(AuthKind::Sql),
+            user: settings.user,
+            password: settings.password,
+            trust_cert: settings.trust_cert.unwrap_or(true),
+            pool_size: settings.pool_size.unwrap_or(8),
+            connection_timeout: std::time::Duration::from_secs(
+                settings.connection_timeout.unwrap_or(5),
+            ),
+        })
+    }
+
+    /// builds the tiberius connection config
+    pub fn tiberius_config(&self) -> Result<tiberius::Config> {
+        let mut config = tiberius::Config::new();
+        config.host(&self.host);
+        config.database(&self.database);
+
+        if let Some(instance) = &self.instance {
+            config.instance_name(instance);
+        }
+        if let Some(port) = self.port {
+            config.port(port);
+        }
+
+        match self.auth {
+            AuthKind::Sql => {
+                let (user, pass) = match (&self.user, &self.password) {
+                    (Some(user), Some(pass)) => (user, pass),
+                    _ => {
+                        return Err(Error::Config(String::from(
+                            "SQL authentication requires SNDB_USER and SNDB_PWD",
+                        )))
+                    }
+                };
+                config.authentication(tiberius::AuthMethod::sql_server(user, pass));
+            }
+            AuthKind::Integrated => config.authentication(tiberius::AuthMethod::Integrated),
+        }
+
+        if self.trust_cert {
+            config.trust_cert();
+        }
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NcStagingMode {
+    /// leave the NC file in the Sigmanest output folder
+    #[default]
+    Copy,
+    /// remove the NC file from the Sigmanest output folder
+    Move,
+}
+
+/// Where NC files are staged from and to when a program is moved to processing
+///
+/// Paths are templates, where `<program>` and `<machine>` are replaced
+/// with the program and machine names.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct NcConfig {
+    #[serde(default)]
+    pub mode: NcStagingMode,
+    /// NC file in the Sigmanest output folder
+    pub source: Option<String>,
+    /// NC file in the machine's queue folder, for machines not in `machines`
+    pub queue: Option<String>,
+    /// queue templates by machine name
+    #[serde(default)]
+    pub machines: HashMap<String, String>,
+}
+
+impl NcConfig {
+    /// loads NC staging config from the config file and environment
+    pub fn load() -> Result<Self> {
+        let mut config = ConfigFile::load()?.nc;
+        let var = |key: &str| std::env::var(key).ok().filter(|val| !val.is_empty());
+
+        match var("SNDB_NC_MODE").as_deref() {
+            None => (),
+            Some("copy") => config.mode = NcStagingMode::Copy,
+            Some("move") => config.mode = NcStagingMode::Move,
+            Some(val) => {
+                return Err(Error::Config(format!(
+                    "SNDB_NC_MODE must be `copy` or `move`: `{}`",
+                    val
+                )))
+            }
+        }
+        config.source = var("SNDB_NC_SOURCE").or(config.source);
+        config.queue = var("SNDB_NC_QUEUE").or(config.queue);
+
+        Ok(config)
+    }
+
+    /// NC staging is disabled if there is no source folder
+    pub fn is_enabled(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// queue template for a machine
+    pub fn queue_template(&self, machine: &str) -> Option<&str> {
+        self.machines
+            .get(machine)
+            .or(self.queue.as_ref())
+            .map(String::as_str)
+    }
+}
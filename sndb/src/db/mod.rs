@@ -0,0 +1,6 @@
+mod pool;
+pub mod row;
+mod transaction;
+
+pub use pool::*;
+pub use transaction::Transaction;
@@ -0,0 +1,136 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bb8::PooledConnection;
+
+use crate::{config::DbConfig, Result};
+
+/// Convenience export of database Pool type
+pub type DbPool = bb8::Pool<ConnectionManager>;
+pub type SqlConn<'a> = PooledConnection<'a, ConnectionManager>;
+
+/// Longest wait between reconnect attempts while the database is down
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Time between health checks while the database is up
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Builds a connection pool for a database
+///
+/// The pool does not connect until it is used, so the server can start
+/// while the database is down. Use [`DbHealth::monitor`] to track when
+/// the database is reachable.
+pub fn build_db_pool(db_config: &DbConfig) -> Result<DbPool> {
+    log::trace!("** init db pool");
+
+    log::debug!(
+        "using database config {:?}: {}/{} ({:?} auth)",
+        db_config.profile,
+        db_config.host,
+        db_config.database,
+        db_config.auth
+    );
+    let config = db_config.tiberius_config()?;
+    let mgr = bb8_tiberius::ConnectionManager::build(config)
+        .map(ConnectionManager)
+        .map_err(|e| crate::Error::Config(e.to_string()))?;
+
+    log::trace!("** > db connection Manager built");
+
+    let pool = bb8::Pool::builder()
+        .max_size(db_config.pool_size)
+        .connection_timeout(db_config.connection_timeout)
+        .test_on_check_out(true)
+        .build_unchecked(mgr);
+
+    log::trace!("** > db pool built");
+
+    Ok(pool)
+}
+
+/// Connection manager that resets connections when they are checked out
+///
+/// A request that is cancelled part way through a [`Transaction`](super::Transaction)
+/// returns its connection to the pool with the transaction still open. Any such
+/// transaction is rolled back before the connection is used again.
+pub struct ConnectionManager(bb8_tiberius::ConnectionManager);
+
+#[async_trait]
+impl bb8::ManageConnection for ConnectionManager {
+    type Connection = bb8_tiberius::rt::Client;
+    type Error = bb8_tiberius::Error;
+
+    async fn connect(&self) -> std::result::Result<Self::Connection, Self::Error> {
+        self.0.connect().await
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
+        conn.simple_query("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION; SET XACT_ABORT OFF; SELECT 1")
+            .await?
+            .into_results()
+            .await?;
+
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        self.0.has_broken(conn)
+    }
+}
+
+/// Tracks if the database is reachable
+#[derive(Debug, Default)]
+pub struct DbHealth {
+    available: AtomicBool,
+}
+
+impl DbHealth {
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    /// checks the database connection, retrying with backoff while it is down
+    pub fn monitor(self: &Arc<Self>, pool: DbPool) {
+        let health = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let mut delay = Duration::from_secs(1);
+
+            loop {
+                match check_connection(&pool).await {
+                    Ok(()) => {
+                        if !health.available.swap(true, Ordering::Relaxed) {
+                            log::info!("database connected");
+                        }
+                        delay = Duration::from_secs(1);
+
+                        tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+                    }
+                    Err(e) => {
+                        if health.available.swap(false, Ordering::Relaxed) {
+                            log::error!("database connection lost: {}", e);
+                        }
+                        log::warn!("database unavailable, retrying in {:?}", delay);
+
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                }
+            }
+        });
+    }
+}
+
+async fn check_connection(pool: &DbPool) -> Result<()> {
+    pool.get()
+        .await?
+        .simple_query("SELECT 1")
+        .await?
+        .into_results()
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Mapping of SQL rows to models, with errors that report the table, row and column
+
+use serde::{Deserialize, Serialize};
+use tiberius::{FromSql, Row};
+
+use crate::{Error, Result};
+
+/// Columns used to identify a row in error messages, in order of preference
+const ROW_ID_COLUMNS: [&str; 6] = [
+    "AutoID",
+    "ArchivePacketID",
+    "ProgramName",
+    "PartName",
+    "SheetName",
+    "RemnantName",
+];
+
+/// A row of a query result from `table`
+pub struct SqlRow<'a> {
+    row: &'a Row,
+    table: &'static str,
+}
+
+impl<'a> SqlRow<'a> {
+    pub fn new(row: &'a Row, table: &'static str) -> Self {
+        Self { row, table }
+    }
+
+    /// gets a value that cannot be NULL
+    pub fn get<T: FromSql<'a>>(&self, column: &str) -> Result<T> {
+        self.get_opt(column)?
+            .ok_or_else(|| self.invalid(column, "is NULL"))
+    }
+
+    /// gets a value that may be NULL
+    pub fn get_opt<T: FromSql<'a>>(&self, column: &str) -> Result<Option<T>> {
+        if !self.has_column(column) {
+            return Err(self.invalid(column, "is missing"));
+        }
+
+        self.row
+            .try_get::<T, _>(column)
+            .map_err(|e| self.invalid(column, &format!("has an unexpected type ({})", e)))
+    }
+
+    /// gets a string that cannot be NULL
+    pub fn get_string(&self, column: &str) -> Result<String> {
+        self.get::<&str>(column).map(String::from)
+    }
+
+    /// gets a string that may be NULL
+    pub fn get_opt_string(&self, column: &str) -> Result<Option<String>> {
+        self.get_opt::<&str>(column).map(|val| val.map(String::from))
+    }
+
+    /// gets the value of the first alias that is in the row and not NULL
+    pub fn get_any<T: FromSql<'a>>(&self, aliases: &[&str]) -> Result<T> {
+        for column in aliases.iter().filter(|col| self.has_column(col)) {
+            if let Some(value) = self.get_opt(column)? {
+                return Ok(value);
+            }
+        }
+
+        Err(self.invalid(&format!("{:?}", aliases), "has no value for any alias"))
+    }
+
+    fn has_column(&self, column: &str) -> bool {
+        self.row.columns().iter().any(|col| col.name() == column)
+    }
+
+    /// describes the row by the first identifying column it has
+    fn row_id(&self) -> String {
+        for column in ROW_ID_COLUMNS.iter().filter(|col| self.has_column(col)) {
+            if let Ok(Some(id)) = self.row.try_get::<i32, _>(*column) {
+                return format!("{}={}", column, id);
+            }
+            if let Ok(Some(id)) = self.row.try_get::<&str, _>(*column) {
+                return format!("{}={}", column, id);
+            }
+        }
+
+        String::from("unidentified row")
+    }
+
+    /// error for a column of this row that has an invalid value
+    pub fn invalid(&self, column: &str, problem: &str) -> Error {
+        Error::RowMapping {
+            table: self.table,
+            row: self.row_id(),
+            column: String::from(column),
+            problem: String::from(problem),
+        }
+    }
+}
+
+/// Maps a row that can come from more than one table (i.e. `Program` or `STPrgArc`),
+/// so mapping errors name the table the row was read from
+pub trait FromSqlRow: Sized {
+    fn from_row(row: &SqlRow) -> Result<Self>;
+}
+
+/// A row left out of a response because it could not be read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    /// `ArchivePacketID` of the nest the row belongs to, if it could be read
+    pub archive_packet_id: Option<i32>,
+    /// names the table, row and column that could not be read
+    pub reason: String,
+}
+
+impl SkippedRow {
+    pub fn new(archive_packet_id: Option<i32>, error: &Error) -> Self {
+        log::warn!("Skipping row: {}", error);
+
+        Self {
+            archive_packet_id,
+            reason: error.to_string(),
+        }
+    }
+}
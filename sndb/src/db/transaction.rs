@@ -0,0 +1,73 @@
+//! Transactions over a pooled connection
+
+use std::ops::{Deref, DerefMut};
+
+use super::SqlConn;
+use crate::Result;
+
+/// An open transaction on a pooled connection
+///
+/// Runs with `XACT_ABORT` on, so SQL Server rolls the whole transaction back
+/// on any error. A transaction that is dropped without [`Transaction::end`]
+/// (e.g. the request was cancelled) is rolled back when the pool next checks
+/// the connection out.
+pub struct Transaction<'a, 'p> {
+    conn: &'a mut SqlConn<'p>,
+    open: bool,
+}
+
+impl<'a, 'p> Transaction<'a, 'p> {
+    pub async fn begin(conn: &'a mut SqlConn<'p>) -> Result<Self> {
+        conn.simple_query("SET XACT_ABORT ON; BEGIN TRANSACTION")
+            .await?
+            .into_results()
+            .await?;
+
+        Ok(Self { conn, open: true })
+    }
+
+    /// commits the transaction if `result` is Ok, otherwise rolls it back
+    pub async fn end<T>(mut self, result: Result<T>) -> Result<T> {
+        let query = match result {
+            Ok(_) => "COMMIT TRANSACTION; SET XACT_ABORT OFF",
+            Err(ref e) => {
+                log::error!("Rolling back transaction: {:?}", e);
+                "IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION; SET XACT_ABORT OFF"
+            }
+        };
+
+        self.open = false;
+        let ended = async { self.conn.simple_query(query).await?.into_results().await };
+        match (ended.await, result) {
+            (Ok(_), result) => result,
+            (Err(e), Ok(_)) => Err(e.into()),
+            // report the error that caused the rollback
+            (Err(e), Err(cause)) => {
+                log::error!("Failed to roll back transaction: {:?}", e);
+                Err(cause)
+            }
+        }
+    }
+}
+
+impl<'p> Deref for Transaction<'_, 'p> {
+    type Target = SqlConn<'p>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl DerefMut for Transaction<'_, '_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+impl Drop for Transaction<'_, '_> {
+    fn drop(&mut self) {
+        if self.open {
+            log::warn!("Transaction dropped while open, it is rolled back on next checkout");
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Header used to pass a correlation id between SAP middleware, clients and the server
+pub const CORRELATION_HEADER: &str = "x-correlation-id";
+/// Longest correlation id accepted from a request
+const MAX_CORRELATION_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+// Error handling: see
+//  https://docs.rs/axum/latest/axum/error_handling/index.html
+//  https://github.com/tokio-rs/axum/blob/main/examples/anyhow-error-response/src/main.rs
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Database error: see server logs.")]
+    SqlError(#[from] tiberius::error::Error),
+    #[error("Database unavailable: could not get a connection, try again later.")]
+    SqlPoolError,
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Conflict(String),
+    /// a SimTrans stored procedure failed or timed out
+    #[error("SimTrans {procedure} {}: see server logs.", if *.timed_out { "timed out" } else { "failed" })]
+    Upstream {
+        procedure: &'static str,
+        timed_out: bool,
+        source: tiberius::error::Error,
+    },
+    /// request rejected by an [extractor](crate::extract)
+    #[error("{message}")]
+    Rejected { status: StatusCode, message: String },
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("NC staging failed: {0}")]
+    NcStaging(String),
+    #[error("Invalid data in {table} ({row}): column `{column}` {problem}")]
+    RowMapping {
+        table: &'static str,
+        row: String,
+        column: String,
+        problem: String,
+    },
+}
+
+impl Error {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::SqlPoolError => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Upstream {
+                timed_out: true, ..
+            } => StatusCode::GATEWAY_TIMEOUT,
+            Self::Upstream { .. } => StatusCode::BAD_GATEWAY,
+            Self::Rejected { status, .. } => *status,
+            Self::SqlError(_) | Self::Config(_) | Self::NcStaging(_) | Self::RowMapping { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Stable error code for clients to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SqlError(_) => "DATABASE_ERROR",
+            Self::SqlPoolError => "DATABASE_UNAVAILABLE",
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::Validation(_) => "VALIDATION_ERROR",
+            Self::Conflict(_) => "CONFLICT",
+            Self::Upstream {
+                timed_out: true, ..
+            } => "SIMTRANS_TIMEOUT",
+            Self::Upstream { .. } => "SIMTRANS_ERROR",
+            Self::Rejected { .. } => "INVALID_REQUEST",
+            Self::Config(_) => "CONFIG_ERROR",
+            Self::NcStaging(_) => "NC_STAGING_ERROR",
+            Self::RowMapping { .. } => "ROW_MAPPING_ERROR",
+        }
+    }
+
+    /// reports a unique key or primary key violation as a conflict
+    pub fn on_duplicate(self, message: impl FnOnce() -> String) -> Self {
+        match &self {
+            // 2601: duplicate key in unique index, 2627: unique constraint or primary key
+            Self::SqlError(tiberius::error::Error::Server(e))
+                if matches!(e.code(), 2601 | 2627) =>
+            {
+                Self::Conflict(message())
+            }
+            _ => self,
+        }
+    }
+
+    /// reports a database error from a SimTrans stored procedure as an upstream failure
+    pub fn upstream(self, procedure: &'static str) -> Self {
+        match self {
+            Self::SqlError(source) => {
+                // 1222: lock request timeout
+                let timed_out = matches!(
+                    &source,
+                    tiberius::error::Error::Io { kind, .. } if *kind == std::io::ErrorKind::TimedOut
+                ) || source.code() == Some(1222);
+
+                Self::Upstream {
+                    procedure,
+                    timed_out,
+                    source,
+                }
+            }
+            _ => self,
+        }
+    }
+}
+
+/// JSON body of error responses
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub correlation_id: String,
+}
+
+// Tell axum how to convert `AppError` into a response.
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let correlation_id = correlation_id();
+        let status = self.status_code();
+
+        match status.is_server_error() {
+            true => log::error!("[{}] {}: {:?}", correlation_id, status, self),
+            false => log::debug!("[{}] {}: {}", correlation_id, status, self),
+        }
+
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            correlation_id,
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
+
+impl<T: std::fmt::Debug> From<bb8::RunError<T>> for Error {
+    fn from(value: bb8::RunError<T>) -> Self {
+        log::error!("Casting bb8 error to app error: {:#?}", value);
+        Self::SqlPoolError
+    }
+}
+
+/// correlation id of the request being handled
+pub fn correlation_id() -> String {
+    CORRELATION_ID
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| new_correlation_id())
+}
+
+fn new_correlation_id() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    format!(
+        "{:x}-{:04x}",
+        millis,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Middleware that scopes each request to a correlation id
+///
+/// Uses the request's `x-correlation-id` header if it is a valid id,
+/// and echoes the id back in the response header.
+pub async fn with_correlation_id(req: Request, next: Next) -> Response {
+    let id = match req.headers().get(CORRELATION_HEADER) {
+        Some(val) => val
+            .to_str()
+            .ok()
+            .filter(|id| is_valid_correlation_id(id))
+            .map(String::from)
+            .unwrap_or_else(|| {
+                let id = new_correlation_id();
+                log::warn!("[{}] ignoring invalid {} header", id, CORRELATION_HEADER);
+                id
+            }),
+        None => new_correlation_id(),
+    };
+
+    let mut response = CORRELATION_ID.scope(id.clone(), next.run(req)).await;
+    if let Ok(val) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(CORRELATION_HEADER, val);
+    }
+
+    response
+}
+
+/// checks a correlation id from a request is safe to log and echo back
+fn is_valid_correlation_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CORRELATION_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io_error(kind: std::io::ErrorKind) -> Error {
+        Error::SqlError(tiberius::error::Error::Io {
+            kind,
+            message: String::from("connection lost"),
+        })
+    }
+
+    #[test]
+    fn status_codes() {
+        let cases = [
+            (
+                Error::NotFound(String::new()),
+                StatusCode::NOT_FOUND,
+                "NOT_FOUND",
+            ),
+            (
+                Error::Validation(String::new()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "VALIDATION_ERROR",
+            ),
+            (
+                Error::Conflict(String::new()),
+                StatusCode::CONFLICT,
+                "CONFLICT",
+            ),
+            (
+                Error::SqlPoolError,
+                StatusCode::SERVICE_UNAVAILABLE,
+                "DATABASE_UNAVAILABLE",
+            ),
+            (
+                Error::Config(String::new()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CONFIG_ERROR",
+            ),
+            (
+                io_error(std::io::ErrorKind::BrokenPipe),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "DATABASE_ERROR",
+            ),
+            (
+                io_error(std::io::ErrorKind::BrokenPipe).upstream("dbo.PushSapDemand"),
+                StatusCode::BAD_GATEWAY,
+                "SIMTRANS_ERROR",
+            ),
+            (
+                io_error(std::io::ErrorKind::TimedOut).upstream("dbo.PushSapDemand"),
+                StatusCode::GATEWAY_TIMEOUT,
+                "SIMTRANS_TIMEOUT",
+            ),
+        ];
+
+        for (error, status, code) in cases {
+            assert_eq!(error.status_code(), status, "{:?}", error);
+            assert_eq!(error.code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn upstream_keeps_other_errors() {
+        let error = Error::Conflict(String::from("duplicate")).upstream("dbo.UpdateProgram");
+        assert!(matches!(error, Error::Conflict(_)));
+
+        let error = io_error(std::io::ErrorKind::TimedOut).upstream("dbo.UpdateProgram");
+        assert_eq!(
+            error.to_string(),
+            "SimTrans dbo.UpdateProgram timed out: see server logs."
+        );
+    }
+}
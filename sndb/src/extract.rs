@@ -0,0 +1,98 @@
+//! Extractors that report rejected requests as [`ErrorBody`](crate::error::ErrorBody)
+//!
+//! Use these instead of the `axum` extractors of the same name, so a bad body
+//! or path gets the same JSON error response as any other error.
+
+use axum::{
+    async_trait,
+    extract::{rejection, FromRequest, FromRequestParts, Request},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+
+use crate::Error;
+
+/// JSON request body or response, see [`axum::Json`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+/// Path parameters, see [`axum::extract::Path`]
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+/// Query string parameters, see [`axum::extract::Query`]
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::from_request(req, state)
+            .await
+            .map(|axum::Json(value)| Self(value))
+            .map_err(|e: rejection::JsonRejection| rejected(e.status(), e.body_text()))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Path::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Path(value)| Self(value))
+            .map_err(|e: rejection::PathRejection| rejected(e.status(), e.body_text()))
+    }
+}
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Query(value)| Self(value))
+            .map_err(|e: rejection::QueryRejection| rejected(e.status(), e.body_text()))
+    }
+}
+
+/// keeps the status and message axum gives a rejection
+fn rejected(status: StatusCode, message: String) -> Error {
+    Error::Rejected { status, message }
+}
+
+/// reads a field of a partial update, where `null` clears the value
+///
+/// Use with `#[serde(default, deserialize_with = "nullable")]` on an
+/// `Option<Option<T>>`: a missing field is `None` (left as is) and `null`
+/// is `Some(None)` (cleared).
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
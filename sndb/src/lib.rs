@@ -0,0 +1,10 @@
+//! Sigmanest database model, row mapping and connection pool, shared by the
+//! SAP interface (`comm`) and the operator interface server
+
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod extract;
+pub mod model;
+
+pub use error::{Error, Result};
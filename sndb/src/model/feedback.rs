@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Nest, Part, Program};
+use crate::{
+    db::{
+        row::{FromSqlRow, SkippedRow, SqlRow},
+        SqlConn,
+    },
+    Error, Result,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[repr(u8)]
+pub enum TransactionType<T> {
+    NotFound,
+    Created(T),
+    Deleted,
+    Updated,
+}
+
+impl<T> TransactionType<T> {
+    pub fn unwrap(self) -> T {
+        match self {
+            TransactionType::Created(value) => value,
+            _ => panic!("Unwrap called on a TransactionType marker variant"),
+        }
+    }
+
+    pub fn tcode(&self) -> &str {
+        match self {
+            TransactionType::NotFound => panic!("NotFound has not TCode"),
+            TransactionType::Created(_) => "SN100",
+            TransactionType::Deleted => "SN101",
+            TransactionType::Updated => "SN102",
+        }
+    }
+
+    /// reads the transaction of a feedback row, reading posted programs with `created`
+    pub fn from_row_with(row: &SqlRow, created: impl FnOnce(&SqlRow) -> Result<T>) -> Result<Self> {
+        match row.get::<&str>("TransType")? {
+            "SN100" => Ok(Self::Created(created(row)?)),
+            "SN101" => Ok(Self::Deleted),
+            "SN102" => Ok(Self::Updated),
+            tcode => Err(row.invalid(
+                "TransType",
+                &format!("`{}` is not a feedback transaction", tcode),
+            )),
+        }
+    }
+}
+
+impl<T: FromSqlRow> FromSqlRow for TransactionType<T> {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Self::from_row_with(row, T::from_row)
+    }
+}
+
+impl<T, O> PartialEq<TransactionType<O>> for TransactionType<T> {
+    fn eq(&self, other: &TransactionType<O>) -> bool {
+        let left = unsafe { *<*const _>::from(self).cast::<u8>() };
+        let right = unsafe { *<*const _>::from(other).cast::<u8>() };
+
+        left == right
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackEntry<T> {
+    pub archive_packet_id: i32,
+    /// SAP batch consumed by the program, if it was assigned one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+    pub state: TransactionType<T>,
+}
+
+impl<T: FromSqlRow> FromSqlRow for FeedbackEntry<T> {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Ok(Self {
+            archive_packet_id: row.get("ArchivePacketID")?,
+            batch: None,
+            state: TransactionType::from_row(row)?,
+        })
+    }
+}
+
+impl<T: FromSqlRow> FeedbackEntry<T> {
+    /// reads feedback rows of a table, leaving out the nests that cannot be read
+    pub fn report(rows: &[tiberius::Row], table: &'static str) -> Result<FeedbackReport<Self>> {
+        FeedbackReport::by_nest(rows.iter().map(|row| {
+            let row = SqlRow::new(row, table);
+
+            (row.get("ArchivePacketID").ok(), Self::from_row(&row))
+        }))
+    }
+}
+
+impl FeedbackEntry<Program> {
+    /// get in process and updated programs from feedback
+    pub async fn get_feedback(conn: &mut SqlConn<'_>) -> Result<FeedbackReport<Self>> {
+        let rows = conn
+            .simple_query(
+                r#"
+select
+	ProgramName, RepeatID,
+	ArchivePacketID, TransType,
+	MachineName, CuttingTime
+from STPrgArc;
+        "#,
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        Self::report(&rows, "STPrgArc")
+    }
+}
+
+impl FeedbackEntry<Part> {
+    /// get in process parts from feedback
+    pub async fn get_ip_feedback(conn: &mut SqlConn<'_>) -> Result<FeedbackReport<Self>> {
+        let rows = conn
+            .simple_query(
+                r#"
+select
+	ArchivePacketID,
+    TransType,
+	STPIPArc.PartName,
+    QtyInProcess as Qty,
+    Data1 as Job,
+    cast(Data2 as int) as Shipment,
+	TrueArea,
+    NestedArea
+from STPIPArc
+inner join Part on Part.PartName=STPIPArc.PartName and Part.WONumber=STPIPArc.WONumber;
+        "#,
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        Self::report(&rows, "STPIPArc")
+    }
+}
+
+impl From<FeedbackEntry<Program>> for FeedbackEntry<Nest> {
+    fn from(value: FeedbackEntry<Program>) -> FeedbackEntry<Nest> {
+        let state = match value.state {
+            TransactionType::Created(_) => {
+                panic!("cannot implicity convert TransactionType with data");
+            }
+            TransactionType::NotFound => TransactionType::NotFound,
+            TransactionType::Deleted => TransactionType::Deleted,
+            TransactionType::Updated => TransactionType::Updated,
+        };
+
+        FeedbackEntry {
+            archive_packet_id: value.archive_packet_id,
+            batch: value.batch,
+            state,
+        }
+    }
+}
+
+impl<T, O> PartialEq<FeedbackEntry<O>> for FeedbackEntry<T> {
+    fn eq(&self, other: &FeedbackEntry<O>) -> bool {
+        self.archive_packet_id == other.archive_packet_id && self.state == other.state
+    }
+}
+
+/// Feedback read for a response, with the rows left out because they could not be read
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackReport<T> {
+    pub feedback: Vec<T>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl<T> FeedbackReport<T> {
+    /// collects mapped feedback rows, keyed by their `ArchivePacketID`
+    ///
+    /// A row that cannot be read leaves out every row of its nest, so a nest
+    /// is reported whole or not at all. Errors other than bad rows are returned.
+    pub fn by_nest(rows: impl IntoIterator<Item = (Option<i32>, Result<T>)>) -> Result<Self> {
+        let mut read = Vec::new();
+        let mut skipped = Vec::new();
+        for (archive_packet_id, row) in rows {
+            match row {
+                Ok(row) => read.push((archive_packet_id, row)),
+                Err(e @ Error::RowMapping { .. }) => {
+                    skipped.push(SkippedRow::new(archive_packet_id, &e))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let is_skipped = |id: &Option<i32>| {
+            id.is_some()
+                && skipped
+                    .iter()
+                    .any(|row: &SkippedRow| row.archive_packet_id == *id)
+        };
+        let feedback = read
+            .into_iter()
+            .filter(|(id, _)| !is_skipped(id))
+            .map(|(_, row)| row)
+            .collect();
+
+        Ok(Self { feedback, skipped })
+    }
+
+    /// leaves out a nest that could not be read, if the error is a bad row
+    pub fn skip_nest(&mut self, archive_packet_id: i32, error: Error) -> Result<()> {
+        match error {
+            Error::RowMapping { .. } => {
+                self.skipped
+                    .push(SkippedRow::new(Some(archive_packet_id), &error));
+
+                Ok(())
+            }
+            e => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad_row(row: &str) -> Error {
+        Error::RowMapping {
+            table: "STPIPArc",
+            row: String::from(row),
+            column: String::from("QtyInProcess"),
+            problem: String::from("is NULL"),
+        }
+    }
+
+    #[test]
+    fn bad_row_skips_its_nest() {
+        let report = FeedbackReport::by_nest([
+            (Some(1), Ok("1-a")),
+            (Some(2), Ok("2-a")),
+            (Some(1), Err(bad_row("AutoID=3"))),
+            (Some(1), Ok("1-b")),
+            (None, Err(bad_row("unidentified row"))),
+            (None, Ok("none")),
+        ])
+        .unwrap();
+
+        assert_eq!(report.feedback, ["2-a", "none"]);
+        assert_eq!(
+            report
+                .skipped
+                .iter()
+                .map(|row| row.archive_packet_id)
+                .collect::<Vec<_>>(),
+            [Some(1), None]
+        );
+        assert!(report.skipped[0].reason.contains("AutoID=3"));
+    }
+
+    #[test]
+    fn other_errors_are_returned() {
+        let report =
+            FeedbackReport::by_nest([(Some(1), Ok(1)), (Some(2), Err(Error::SqlPoolError))]);
+
+        assert!(matches!(report, Err(Error::SqlPoolError)));
+    }
+}
@@ -0,0 +1,13 @@
+mod feedback;
+mod nest;
+mod part;
+mod program;
+mod remnant;
+mod sheet;
+
+pub use feedback::{FeedbackEntry, FeedbackReport, TransactionType};
+pub use nest::{Nest, NestRows};
+pub use part::Part;
+pub use program::Program;
+pub use remnant::Remnant;
+pub use sheet::{ProgramSheet, Sheet, SheetSize};
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Part, Program, Remnant, Sheet};
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        SqlConn,
+    },
+    Error, Result,
+};
+
+/// A posted program with its parts, sheet and remnants
+///
+/// The SAP interface reports feedback rows (with their `AutoID`s) as the
+/// program and parts, and every sheet of a combined nest.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Nest<P = Program, T = Part, S = Sheet> {
+    pub archive_packet_id: i32,
+    pub program: P,
+    pub parts: Vec<T>,
+    pub sheet: S,
+    pub remnants: Vec<Remnant>,
+}
+
+/// Rows read for a program repeat by [`Nest::get`], one list per query
+///
+/// `program` is the first `Program` row, with its `ArchivePacketID`.
+#[derive(Debug, Default)]
+pub struct NestRows {
+    pub program: Option<Result<(i32, Program)>>,
+    pub parts: Vec<Result<Part>>,
+    pub sheets: Vec<Result<Sheet>>,
+    pub remnants: Vec<Result<Remnant>>,
+}
+
+impl Nest {
+    /// get a program repeat with its parts, sheet and remnants
+    pub async fn get(conn: &mut SqlConn<'_>, program_name: &str, repeat_id: i32) -> Result<Self> {
+        let mut results = conn
+            .query(
+                r#"
+select
+	ProgramName, RepeatID, ArchivePacketID,
+	MachineName, CuttingTime
+from Program
+where ProgramName=@P1 and RepeatID=@P2;
+select distinct
+	ProgramName,
+	PIP.WONumber, PIP.PartName, QtyInProcess as Qty,
+	Data1 as Job, cast(Data2 as int) as Shipment,
+	TrueArea, NestedArea
+from PIP
+inner join Part on PIP.PartName=Part.PartName
+where ProgramName=@P1 and RepeatID=@P2;
+select distinct
+	Stock.SheetName, PrimeCode as MaterialMaster
+from Stock
+inner join Program on Stock.SheetName=Program.SheetName
+where ProgramName=@P1 and RepeatID=@P2;
+select distinct
+	RemnantName, ProgramName,
+	Length, Width, Area, Weight,
+	PrimeCode, Qty
+from Remnant
+where ProgramName=@P1 and RepeatID=@P2;
+    "#,
+                &[&program_name, &repeat_id],
+            )
+            .await?
+            .into_results()
+            .await
+            .map(|res| {
+                log::trace!("{:#?}", res);
+                res
+            })?
+            .into_iter();
+
+        let programs = results.next().unwrap_or_default();
+        let parts = results.next().unwrap_or_default();
+        let sheets = results.next().unwrap_or_default();
+        let remnants = results.next().unwrap_or_default();
+
+        let rows = NestRows {
+            program: programs.first().map(|row| {
+                let program = Program::try_from(row)?;
+
+                Ok((SqlRow::new(row, "Program").get("ArchivePacketID")?, program))
+            }),
+            parts: parts.iter().map(Part::try_from).collect(),
+            sheets: sheets.iter().map(Sheet::try_from).collect(),
+            remnants: remnants.iter().map(Remnant::try_from).collect(),
+        };
+
+        Self::from_rows(program_name, repeat_id, rows)
+    }
+
+    /// assembles a program repeat from the rows [`Nest::get`] reads
+    ///
+    /// The sheet is the last sheet row, and any part or remnant that cannot be
+    /// read fails the whole nest.
+    pub fn from_rows(program_name: &str, repeat_id: i32, rows: NestRows) -> Result<Self> {
+        let (archive_packet_id, program) = rows.program.unwrap_or_else(|| {
+            Err(Error::NotFound(format!(
+                "Program {} repeat {} not found",
+                program_name, repeat_id
+            )))
+        })?;
+
+        let parts = rows.parts.into_iter().collect::<Result<Vec<Part>>>()?;
+
+        let sheet = rows.sheets.into_iter().last().unwrap_or_else(|| {
+            Err(Error::NotFound(format!(
+                "No sheet found for program {} repeat {}",
+                program_name, repeat_id
+            )))
+        })?;
+
+        let remnants = rows
+            .remnants
+            .into_iter()
+            .collect::<Result<Vec<Remnant>>>()?;
+
+        Ok(Nest {
+            archive_packet_id,
+            program,
+            parts,
+            sheet,
+            remnants,
+        })
+    }
+
+    /// get a program repeat by its `ArchivePacketID`
+    pub async fn get_by_archive_packet_id(
+        conn: &mut SqlConn<'_>,
+        archive_packet_id: i32,
+    ) -> Result<Self> {
+        let row = conn
+            .query(
+                r#"
+select ProgramName, RepeatID
+from Program
+where ArchivePacketID=@P1;
+                "#,
+                &[&archive_packet_id],
+            )
+            .await?
+            .into_row()
+            .await?
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "No program with ArchivePacketID {}",
+                    archive_packet_id
+                ))
+            })?;
+
+        let row = SqlRow::new(&row, "Program");
+        let program = row.get_string("ProgramName")?;
+        let repeat_id = row.get("RepeatID")?;
+
+        Self::get(conn, &program, repeat_id).await
+    }
+}
+
+impl FromSqlRow for Nest {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Ok(Nest {
+            archive_packet_id: row.get("ArchivePacketID")?,
+            program: Program::from_row(row)?,
+            parts: Vec::new(),
+            sheet: Sheet::from_row(row)?,
+            remnants: Vec::new(),
+        })
+    }
+}
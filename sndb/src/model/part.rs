@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use super::FeedbackReport;
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        SqlConn,
+    },
+    Result,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Part {
+    pub part_name: String,
+    pub part_qty: i32,
+    pub job: String,
+    pub shipment: i32,
+    pub true_area: f64,
+    pub nested_area: f64,
+}
+
+impl Part {
+    /// get in process part from feedback
+    ///
+    /// Fails if any part cannot be read, so the nest can be left out as a whole.
+    pub async fn get_ip_feedback_by_program(
+        conn: &mut SqlConn<'_>,
+        id: i32,
+        tcode: String,
+        // program: &FeedbackEntry<Program>,
+    ) -> Result<Vec<Self>> {
+        conn
+            .query(
+            r#"
+select
+	STPIPArc.PartName,
+    QtyInProcess as Qty,
+    Data1 as Job,
+    cast(Data2 as int) as Shipment,
+	TrueArea,
+    NestedArea
+from STPIPArc
+inner join Part on Part.PartName=STPIPArc.PartName and Part.WONumber=STPIPArc.WONumber
+where ArchivePacketID=@P1 and TransType=@P2;
+        "#,
+                &[&id, &tcode],
+            )
+            .await?
+            .into_first_result()
+            .await?
+            .iter()
+            .map(|row| Self::from_row(&SqlRow::new(row, "STPIPArc")))
+            .collect()
+    }
+
+    /// get updated parts from feedback
+    pub async fn get_complete_feedback(conn: &mut SqlConn<'_>) -> Result<FeedbackReport<Self>> {
+        let rows = conn
+            .simple_query(
+            r#"
+select
+	ArchivePacketID,
+	PartName,
+    QtyProgram as Qty,
+    Data1 as Job,
+    cast(Data2 as int) as Shipment,
+	TrueArea,
+    NestedArea
+from STPrtArc;
+        "#,
+            )
+            .await?
+            .into_first_result()
+            .await?;
+
+        FeedbackReport::by_nest(rows.iter().map(|row| {
+            let row = SqlRow::new(row, "STPrtArc");
+
+            (row.get("ArchivePacketID").ok(), Self::from_row(&row))
+        }))
+    }
+}
+
+impl TryFrom<&tiberius::Row> for Part {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        Self::from_row(&SqlRow::new(row, "Part"))
+    }
+}
+
+impl FromSqlRow for Part {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Ok(Self {
+            part_name: row.get_opt_string("PartName")?.unwrap_or_default(),
+            part_qty: row.get("Qty")?,
+            job: row.get_opt_string("Job")?.unwrap_or_default(),
+            shipment: row.get_opt("Shipment")?.unwrap_or_default(),
+            true_area: row.get("TrueArea")?,
+            nested_area: row.get("NestedArea")?,
+        })
+    }
+}
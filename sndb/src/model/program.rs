@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        SqlConn,
+    },
+    Error, Result,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Program {
+    pub program_name: String,
+    pub repeat_id: i32,
+    pub machine_name: String,
+    pub cutting_time: f64,
+}
+
+impl Program {
+    /// get a program repeat
+    pub async fn get(conn: &mut SqlConn<'_>, program: &str, repeat_id: i32) -> Result<Self> {
+        conn.query(
+            r#"
+select
+	ProgramName, RepeatID,
+	MachineName, CuttingTime
+from Program
+where ProgramName=@P1 and RepeatID=@P2;
+            "#,
+            &[&program, &repeat_id],
+        )
+        .await?
+        .into_row()
+        .await?
+        .as_ref()
+        .map(Self::try_from)
+        .unwrap_or_else(|| {
+            Err(Error::NotFound(format!(
+                "Program {} repeat {} not found",
+                program, repeat_id
+            )))
+        })
+    }
+}
+
+impl TryFrom<&tiberius::Row> for Program {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        Self::from_row(&SqlRow::new(row, "Program"))
+    }
+}
+
+impl FromSqlRow for Program {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Ok(Self {
+            program_name: row.get_string("ProgramName")?,
+            repeat_id: row.get("RepeatID")?,
+            machine_name: row.get_string("MachineName")?,
+            cutting_time: row.get("CuttingTime")?,
+        })
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{db::SqlConn, Result};
+use crate::{
+    db::{row::SqlRow, SqlConn},
+    Result,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,11 +33,11 @@ from Remnant;
         .await?
         .iter()
         .map(|row| {
+            let remnant = SqlRow::new(row, "Remnant");
+
             Ok((
-                row.try_get::<&str, _>("ProgramName")?
-                    .map(Into::into)
-                    .unwrap(),
-                row.try_get::<i32, _>("RepeatID")?.map(Into::into).unwrap(),
+                remnant.get_string("ProgramName")?,
+                remnant.get_any(&["RepeatID", "RepeatId"])?,
                 Self::try_from(row)?,
             ))
         })
@@ -42,6 +45,8 @@ from Remnant;
     }
 
     /// get remnants to be created by a program
+    ///
+    /// Fails if any remnant cannot be read, so the nest can be left out as a whole.
     pub async fn get_future_remnants_by_program(
         conn: &mut SqlConn<'_>,
         program: String,
@@ -72,14 +77,13 @@ impl TryFrom<&tiberius::Row> for Remnant {
     type Error = crate::Error;
 
     fn try_from(row: &tiberius::Row) -> Result<Self> {
+        let row = SqlRow::new(row, "Remnant");
+
         Ok(Self {
-            remnant_name: row
-                .try_get::<&str, _>("RemnantName")?
-                .map(Into::into)
-                .unwrap(),
-            length: row.try_get("Length")?.unwrap(),
-            width: row.try_get("Width")?.unwrap(),
-            area: row.try_get("Area")?.unwrap(),
+            remnant_name: row.get_string("RemnantName")?,
+            length: row.get("Length")?,
+            width: row.get("Width")?,
+            area: row.get("Area")?,
         })
     }
 }
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{
+    db::{
+        row::{FromSqlRow, SqlRow},
+        SqlConn,
+    },
+    Result,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sheet {
+    pub sheet_name: String,
+    pub material_master: String,
+}
+
+/// A sheet a program repeat is nested on
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramSheet {
+    #[serde(flatten)]
+    pub sheet: Sheet,
+    pub is_remnant: bool,
+}
+
+/// Size of a sheet a program is nested on, used to check machine capabilities
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetSize {
+    pub sheet_name: String,
+    pub thickness: f64,
+    pub width: f64,
+    pub length: f64,
+}
+
+impl ProgramSheet {
+    /// standard sheets are named by their material master, singleton sheets have their own name
+    pub fn is_standard(&self) -> bool {
+        !self.is_remnant && self.sheet.sheet_name == self.sheet.material_master
+    }
+}
+
+impl Sheet {
+    /// get all sheets used by a program repeat
+    ///
+    /// Combined sheets have a different `Program.SheetName` than the sheets
+    /// they were combined from, so this uses `SIP` instead.
+    ///
+    /// Remnants in stock have their geometry in `Stock.FileName` (i.e. loaded
+    /// with `SN97`), and remnants of programs not cut yet are in `Remnant`.
+    pub async fn get_by_program(
+        conn: &mut SqlConn<'_>,
+        program: &str,
+        repeat_id: i32,
+    ) -> Result<Vec<ProgramSheet>> {
+        conn.query(
+            r#"
+select distinct
+	Stock.SheetName,
+	PrimeCode as MaterialMaster,
+	case
+		when Stock.FileName is not null then 1
+		when exists (select 1 from Remnant where RemnantName=Stock.SheetName) then 1
+		else 0
+	end as IsRemnant
+from SIP
+inner join Stock on SIP.SheetName=Stock.SheetName
+where SIP.ProgramName=@P1 and SIP.RepeatID=@P2;
+            "#,
+            &[&program, &repeat_id],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(ProgramSheet {
+                sheet: Self::try_from(row)?,
+                is_remnant: SqlRow::new(row, "Stock").get::<i32>("IsRemnant")? == 1,
+            })
+        })
+        .collect()
+    }
+
+    /// get the sizes of all sheets used by any repeat of a program
+    pub async fn get_sizes_by_program(
+        conn: &mut SqlConn<'_>,
+        program: &str,
+    ) -> Result<Vec<SheetSize>> {
+        conn.query(
+            r#"
+select distinct
+	Stock.SheetName,
+	Thickness,
+	Width,
+	Length
+from SIP
+inner join Stock on SIP.SheetName=Stock.SheetName
+where SIP.ProgramName=@P1;
+            "#,
+            &[&program],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(|row| {
+            let row = SqlRow::new(row, "Stock");
+
+            Ok(SheetSize {
+                sheet_name: row.get_string("SheetName")?,
+                thickness: row.get("Thickness")?,
+                width: row.get("Width")?,
+                length: row.get("Length")?,
+            })
+        })
+        .collect()
+    }
+
+    /// get in process sheets
+    pub async fn get_ip_sheets(conn: &mut SqlConn<'_>) -> Result<HashMap<String, Self>> {
+        conn.simple_query(
+            r#"
+select
+	ProgramName,
+	Stock.SheetName,
+	PrimeCode as MaterialMaster
+from Stock
+inner join STPrgArc on STPrgArc.SheetName=Stock.SheetName
+        "#,
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(|row| {
+            Ok((
+                SqlRow::new(row, "STPrgArc").get_string("ProgramName")?,
+                Self::try_from(row)?,
+            ))
+        })
+        .collect()
+    }
+
+    /// get sheets used by a posted program
+    pub async fn get_feedback_by_program(
+        conn: &mut SqlConn<'_>,
+        archive_packet_id: i32,
+    ) -> Result<Vec<Self>> {
+        conn.query(
+            r#"
+SELECT
+    Stock.SheetName,
+    Stock.PrimeCode AS MaterialMaster
+FROM STPrgArc
+INNER JOIN SIP
+    ON STPrgArc.ProgramName=SIP.ProgramName
+    AND STPrgArc.RepeatID=SIP.RepeatID
+-- cannot match on STPrgArc.SheetName because combined sheets will differ
+INNER JOIN Stock
+    ON SIP.SheetName=Stock.SheetName
+WHERE STPrgArc.ArchivePacketID=@P1
+AND STPrgArc.TransType='SN100'
+        "#,
+            &[&archive_packet_id],
+        )
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .map(Self::try_from)
+        .collect()
+    }
+}
+
+impl TryFrom<&tiberius::Row> for Sheet {
+    type Error = crate::Error;
+
+    fn try_from(row: &tiberius::Row) -> Result<Self> {
+        Self::from_row(&SqlRow::new(row, "Stock"))
+    }
+}
+
+impl FromSqlRow for Sheet {
+    fn from_row(row: &SqlRow) -> Result<Self> {
+        Ok(Self {
+            sheet_name: row.get_opt_string("SheetName")?.unwrap_or_default(),
+            material_master: row.get_opt_string("MaterialMaster")?.unwrap_or_default(),
+        })
+    }
+}